@@ -0,0 +1,122 @@
+use serenity::model::channel::Attachment;
+use tracing::{debug, warn};
+
+use super::providers::{ChatMessage, Part};
+
+/// Attachments beyond this many are ignored.
+const MAX_ATTACHMENTS: usize = 4;
+
+/// Longest text file inlined into the prompt, in characters.
+const MAX_INLINE_TEXT_CHARS: usize = 20_000;
+
+const IMAGE_TYPES: &[(&str, &str)] = &[
+    ("png", "image/png"),
+    ("jpg", "image/jpeg"),
+    ("jpeg", "image/jpeg"),
+    ("webp", "image/webp"),
+    ("heic", "image/heic"),
+    ("heif", "image/heif"),
+];
+
+/// Text file extensions and the code fence language used when inlining them.
+const TEXT_TYPES: &[(&str, &str)] = &[
+    ("lua", "lua"),
+    ("luau", "lua"),
+    ("txt", ""),
+    ("log", ""),
+    ("md", "md"),
+    ("json", "json"),
+    ("toml", "toml"),
+    ("xml", "xml"),
+    ("rbxmx", "xml"),
+    ("rbxlx", "xml"),
+];
+
+enum Kind {
+    Image(&'static str),
+    Text(&'static str),
+}
+
+fn classify(attachment: &Attachment) -> Option<Kind> {
+    let extension = attachment.filename
+        .rsplit_once('.')
+        .map(|(_, ext)| ext.to_lowercase())
+        .unwrap_or_default();
+    let content_type = attachment.content_type.as_deref().unwrap_or_default();
+
+    if let Some((_, mime)) = IMAGE_TYPES.iter().find(|(ext, mime)| *ext == extension || content_type.starts_with(mime)) {
+        return Some(Kind::Image(mime));
+    }
+    if let Some((_, lang)) = TEXT_TYPES.iter().find(|(ext, _)| *ext == extension) {
+        return Some(Kind::Text(lang));
+    }
+    if content_type.starts_with("text/") {
+        return Some(Kind::Text(""));
+    }
+    None
+}
+
+/// Builds the user message for `content` plus whatever attachments can be shared
+/// with the model. Images become inline data parts; text and script files are
+/// inlined as fenced code. Skipped attachments are noted so the model knows they
+/// exist. Everything about attachments goes in parts after the message text, so a
+/// message with a single part is plain text.
+pub async fn build_message(content: &str, attachments: &[Attachment], max_bytes: u64) -> ChatMessage {
+    let mut text = String::new();
+    let mut images = Vec::new();
+
+    for (index, attachment) in attachments.iter().enumerate() {
+        let name = &attachment.filename;
+
+        if index >= MAX_ATTACHMENTS {
+            text.push_str(&format!("\n\n[Attachment {} skipped: only {} attachments are read per message]", name, MAX_ATTACHMENTS));
+            continue;
+        }
+
+        let Some(kind) = classify(attachment) else {
+            text.push_str(&format!("\n\n[Attachment {} skipped: unsupported file type]", name));
+            continue;
+        };
+
+        if u64::from(attachment.size) > max_bytes {
+            text.push_str(&format!("\n\n[Attachment {} skipped: larger than {} KB]", name, max_bytes / 1024));
+            continue;
+        }
+
+        let data = match attachment.download().await {
+            Ok(data) => data,
+            Err(e) => {
+                warn!("Failed to download attachment {}: {}", name, e);
+                text.push_str(&format!("\n\n[Attachment {} could not be downloaded]", name));
+                continue;
+            }
+        };
+
+        debug!("Read attachment {} ({} bytes)", name, data.len());
+
+        match kind {
+            Kind::Image(mime_type) => {
+                text.push_str(&format!("\n\n[Attached image: {}]", name));
+                images.push(Part::InlineData {
+                    mime_type: mime_type.to_string(),
+                    data,
+                });
+            }
+            Kind::Text(lang) => {
+                let contents = String::from_utf8_lossy(&data);
+                let mut inlined: String = contents.chars().take(MAX_INLINE_TEXT_CHARS).collect();
+                if inlined.len() < contents.len() {
+                    inlined.push_str("\n-- (file truncated)");
+                }
+                text.push_str(&format!("\n\nAttached file {}:\n```{}\n{}\n```", name, lang, inlined.trim_end()));
+            }
+        }
+    }
+
+    let mut message = ChatMessage::user(content);
+    if !text.is_empty() {
+        message.parts.push(Part::Text(text));
+    }
+    message.parts.extend(images);
+    message
+}
@@ -0,0 +1,143 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::debug;
+
+/// Greetings and filler stripped from questions before they are used as keys.
+const FILLER_WORDS: &[&str] = &["hey", "hi", "hello", "yo", "please", "pls", "plz", "thanks", "thx"];
+
+struct Entry {
+    response: String,
+    created: Instant,
+    last_used: u64,
+}
+
+/// Snapshot of the cache counters.
+#[derive(Debug, Clone, Copy)]
+pub struct CacheStats {
+    pub entries: usize,
+    pub capacity: usize,
+    pub hits: u64,
+    pub misses: u64,
+    pub ttl: Duration,
+}
+
+impl CacheStats {
+    pub fn hit_rate(&self) -> f64 {
+        let lookups = self.hits + self.misses;
+        if lookups == 0 { 0.0 } else { self.hits as f64 / lookups as f64 }
+    }
+}
+
+/// Answers to standalone questions, keyed by the normalized question and the
+/// persona template, detail level and safety settings that answered it. Entries
+/// expire after `ttl`; when full, the least recently used entry is evicted. A
+/// capacity of zero disables the cache.
+pub struct ResponseCache {
+    entries: Mutex<HashMap<String, Entry>>,
+    /// Logical clock for recency, bumped on every access.
+    clock: AtomicU64,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    capacity: usize,
+    ttl: Duration,
+}
+
+impl ResponseCache {
+    pub fn new(capacity: usize, ttl: Duration) -> Self {
+        Self {
+            entries: Mutex::new(HashMap::new()),
+            clock: AtomicU64::new(0),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            capacity,
+            ttl,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.capacity > 0
+    }
+
+    /// Cache key for `question` asked of `persona`, or `None` when nothing
+    /// meaningful is left after normalization.
+    pub fn key(question: &str, bot_name: &str, persona: &str) -> Option<String> {
+        let bot_name = bot_name.to_lowercase();
+        let normalized = question.to_lowercase()
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|word| !word.is_empty() && *word != bot_name && !FILLER_WORDS.contains(word))
+            .collect::<Vec<_>>()
+            .join(" ");
+        if normalized.is_empty() {
+            return None;
+        }
+        Some(format!("{:016x}\u{0}{}", fingerprint(persona), normalized))
+    }
+
+    pub fn get(&self, key: &str) -> Option<String> {
+        if !self.is_enabled() {
+            return None;
+        }
+
+        let mut entries = self.entries.lock().unwrap();
+        let fresh = entries.get(key).is_some_and(|entry| entry.created.elapsed() < self.ttl);
+        if !fresh {
+            entries.remove(key);
+            self.misses.fetch_add(1, Ordering::Relaxed);
+            return None;
+        }
+
+        self.hits.fetch_add(1, Ordering::Relaxed);
+        let tick = self.clock.fetch_add(1, Ordering::Relaxed);
+        entries.get_mut(key).map(|entry| {
+            entry.last_used = tick;
+            entry.response.clone()
+        })
+    }
+
+    pub fn insert(&self, key: String, response: String) {
+        if !self.is_enabled() {
+            return;
+        }
+
+        let mut entries = self.entries.lock().unwrap();
+        entries.retain(|_, entry| entry.created.elapsed() < self.ttl);
+        if entries.len() >= self.capacity && !entries.contains_key(&key) {
+            let oldest = entries.iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                debug!("Evicting least recently used cached response");
+                entries.remove(&oldest);
+            }
+        }
+
+        let tick = self.clock.fetch_add(1, Ordering::Relaxed);
+        entries.insert(key, Entry { response, created: Instant::now(), last_used: tick });
+    }
+
+    /// Drops every entry and returns how many there were. Counters are kept.
+    pub fn purge(&self) -> usize {
+        let mut entries = self.entries.lock().unwrap();
+        let count = entries.len();
+        entries.clear();
+        count
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        let entries = self.entries.lock().unwrap();
+        CacheStats {
+            entries: entries.values().filter(|entry| entry.created.elapsed() < self.ttl).count(),
+            capacity: self.capacity,
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            ttl: self.ttl,
+        }
+    }
+}
+
+/// FNV-1a, so long personas do not bloat every key.
+fn fingerprint(text: &str) -> u64 {
+    text.bytes().fold(0xcbf29ce484222325, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x100000001b3))
+}
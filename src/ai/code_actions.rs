@@ -0,0 +1,75 @@
+use serenity::model::channel::Message;
+
+use super::postprocess;
+
+/// What the message context-menu commands do with a message's code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CodeAction {
+    Explain,
+    Review,
+}
+
+impl CodeAction {
+    pub const ALL: [CodeAction; 2] = [CodeAction::Explain, CodeAction::Review];
+
+    /// Name shown under Apps when right-clicking a message, which is also the command name.
+    pub fn command_name(&self) -> &'static str {
+        match self {
+            CodeAction::Explain => "Explain code",
+            CodeAction::Review => "Review code",
+        }
+    }
+
+    pub fn from_command_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|action| action.command_name() == name)
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            CodeAction::Explain => "Code explanation",
+            CodeAction::Review => "Code review",
+        }
+    }
+
+    fn instruction(&self) -> &'static str {
+        match self {
+            CodeAction::Explain => "Explain what the following Roblox Luau code does, section by section, for the developer \
+                who is reading it. Mention which parts run on the server or client, and point out anything surprising. \
+                Do not rewrite the code unless something in it is broken.",
+            CodeAction::Review => "Review the following Roblox Luau code. List concrete problems ordered by severity: bugs, \
+                security issues (such as trusting values sent through RemoteEvents), performance, deprecated APIs \
+                (such as wait, spawn and delay instead of the task library) and readability. Refer to the lines involved \
+                and show short fixes. If the code looks good, say so briefly.",
+        }
+    }
+}
+
+/// Prompt asking for `action` on the code in `message`: its fenced blocks, or its
+/// whole text when it has none. Attached files are added separately by the caller.
+/// Returns `None` when the message has nothing to work with.
+pub fn prompt(action: CodeAction, message: &Message) -> Option<String> {
+    let blocks = postprocess::code_blocks(&message.content);
+    let code = if blocks.is_empty() {
+        let content = message.content.trim();
+        if content.is_empty() && message.attachments.is_empty() {
+            return None;
+        }
+        (!content.is_empty()).then(|| format!("```lua\n{}\n```", content))
+    } else {
+        let blocks = blocks.iter()
+            .map(|block| {
+                let lang = if block.lang.is_empty() { "lua" } else { &block.lang };
+                format!("```{}\n{}```", lang, block.code)
+            })
+            .collect::<Vec<_>>();
+        Some(blocks.join("\n\n"))
+    };
+
+    let author = message.author.global_name.as_ref().unwrap_or(&message.author.name);
+    let mut prompt = format!("{}\n\nCode shared by {}:", action.instruction(), author);
+    match code {
+        Some(code) => prompt.push_str(&format!("\n{}", code)),
+        None => prompt.push_str(" (see the attached files)"),
+    }
+    Some(prompt)
+}
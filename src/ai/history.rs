@@ -0,0 +1,88 @@
+use std::collections::VecDeque;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    User,
+    Model,
+}
+
+impl Role {
+    pub fn as_gemini_role(&self) -> &'static str {
+        match self {
+            Role::User => "user",
+            Role::Model => "model",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Turn {
+    pub role: Role,
+    pub text: String,
+}
+
+impl Turn {
+    pub fn user(text: impl Into<String>) -> Self {
+        Self { role: Role::User, text: text.into() }
+    }
+
+    pub fn model(text: impl Into<String>) -> Self {
+        Self { role: Role::Model, text: text.into() }
+    }
+}
+
+/// Bounded record of the turns exchanged in a single conversation.
+///
+/// The oldest turns are dropped once either the turn limit or the character
+/// budget is exceeded, so the request sent to the model stays a predictable size.
+#[derive(Debug, Clone)]
+pub struct ConversationHistory {
+    turns: VecDeque<Turn>,
+    max_turns: usize,
+    char_budget: usize,
+}
+
+impl ConversationHistory {
+    pub fn new(max_turns: usize, char_budget: usize) -> Self {
+        Self {
+            turns: VecDeque::new(),
+            max_turns,
+            char_budget,
+        }
+    }
+
+    pub fn push(&mut self, turn: Turn) {
+        self.turns.push_back(turn);
+        self.trim();
+    }
+
+    pub fn turns(&self) -> Vec<Turn> {
+        self.turns.iter().cloned().collect()
+    }
+
+    /// Swaps the model's latest answer for `new` while it is still `old`, as when
+    /// the user regenerates it.
+    pub fn replace_last_response(&mut self, old: &str, new: &str) {
+        if let Some(turn) = self.turns.back_mut().filter(|turn| turn.role == Role::Model && turn.text == old) {
+            turn.text = new.to_string();
+            self.trim();
+        }
+    }
+
+    fn total_chars(&self) -> usize {
+        self.turns.iter().map(|turn| turn.text.chars().count()).sum()
+    }
+
+    fn trim(&mut self) {
+        while !self.turns.is_empty()
+            && (self.turns.len() > self.max_turns || self.total_chars() > self.char_budget)
+        {
+            self.turns.pop_front();
+        }
+
+        // Gemini expects the contents to open with a user turn
+        while self.turns.front().is_some_and(|turn| turn.role == Role::Model) {
+            self.turns.pop_front();
+        }
+    }
+}
@@ -0,0 +1,291 @@
+use rand::Rng;
+use reqwest::{Client, RequestBuilder, Response, StatusCode};
+use serde_json::Value;
+use std::fmt;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::{debug, error, warn};
+
+/// Why a provider withheld an answer it generated.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BlockReason {
+    Safety,
+    /// The answer reproduced existing published content too closely.
+    Recitation,
+    Other(String),
+}
+
+/// Failure modes of a request to an AI provider.
+#[derive(Debug)]
+pub enum AiError {
+    Timeout,
+    RateLimited { retry_after: Option<Duration> },
+    Server { status: u16, body: String },
+    Client { status: u16, body: String },
+    Network(String),
+    InvalidResponse(String),
+    CircuitOpen { retry_in: Duration },
+    /// The provider refused to process the prompt; `categories` are the flagged harm categories.
+    PromptBlocked { reason: String, categories: Vec<String> },
+    /// The provider generated an answer but withheld it.
+    ResponseBlocked { reason: BlockReason, categories: Vec<String> },
+    /// The provider answered without any text and without saying why.
+    EmptyResponse,
+}
+
+impl AiError {
+    /// Request URLs can carry credentials, so they are left out of the message.
+    fn from_reqwest(e: reqwest::Error) -> Self {
+        if e.is_timeout() {
+            AiError::Timeout
+        } else {
+            AiError::Network(e.without_url().to_string())
+        }
+    }
+
+    /// Whether the request may succeed if sent again.
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            AiError::Timeout | AiError::RateLimited { .. } | AiError::Server { .. } | AiError::Network(_)
+        )
+    }
+
+    /// Message shown to Discord users when their request fails with this error.
+    pub fn user_message(&self) -> String {
+        match self {
+            AiError::Timeout => "Request timed out. Please try again.".to_string(),
+            AiError::RateLimited { .. } => "The AI service is receiving too many requests. Please try again in a moment.".to_string(),
+            AiError::CircuitOpen { .. } => "The AI service is currently unavailable. Please try again in a few minutes.".to_string(),
+            AiError::PromptBlocked { categories, .. } => format!(
+                "Your message was blocked by the AI provider's safety filters{}. Please rephrase it and try again.",
+                Self::category_suffix(categories)
+            ),
+            AiError::ResponseBlocked { reason: BlockReason::Safety, categories } => format!(
+                "My answer was withheld by the safety filters{}. Try rephrasing your question.",
+                Self::category_suffix(categories)
+            ),
+            AiError::ResponseBlocked { reason: BlockReason::Recitation, .. } => {
+                "My answer was withheld because it closely matched existing published content. \
+                Try asking for an explanation instead of the exact text.".to_string()
+            }
+            AiError::ResponseBlocked { reason: BlockReason::Other(_), .. } => {
+                "The AI provider withheld my answer to this message. Try rephrasing your question.".to_string()
+            }
+            AiError::EmptyResponse => "I didn't get an answer back this time. Please try again or rephrase your question.".to_string(),
+            _ => "I'm having trouble processing your request right now.".to_string(),
+        }
+    }
+
+    fn category_suffix(categories: &[String]) -> String {
+        if categories.is_empty() {
+            String::new()
+        } else {
+            format!(" ({})", categories.join(", "))
+        }
+    }
+}
+
+impl fmt::Display for AiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AiError::Timeout => write!(f, "AI request timed out"),
+            AiError::RateLimited { retry_after: Some(delay) } => write!(f, "AI provider rate limited the request (retry after {:?})", delay),
+            AiError::RateLimited { retry_after: None } => write!(f, "AI provider rate limited the request"),
+            AiError::Server { status, body } => write!(f, "AI provider server error {}: {}", status, body),
+            AiError::Client { status, body } => write!(f, "AI provider rejected the request {}: {}", status, body),
+            AiError::Network(e) => write!(f, "Network error talking to AI provider: {}", e),
+            AiError::InvalidResponse(e) => write!(f, "Invalid response from AI provider: {}", e),
+            AiError::CircuitOpen { retry_in } => write!(f, "AI provider circuit breaker open (retry in {:?})", retry_in),
+            AiError::PromptBlocked { reason, categories } => write!(f, "AI provider blocked the prompt ({}): {:?}", reason, categories),
+            AiError::ResponseBlocked { reason, categories } => write!(f, "AI provider withheld the response ({:?}): {:?}", reason, categories),
+            AiError::EmptyResponse => write!(f, "AI provider returned no text"),
+        }
+    }
+}
+
+impl std::error::Error for AiError {}
+
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl RetryPolicy {
+    /// Exponential backoff with full jitter for the given (zero-based) retry attempt.
+    fn backoff(&self, attempt: u32) -> Duration {
+        let ceiling = self.base_delay
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.max_delay);
+        let millis = rand::thread_rng().gen_range(0..=ceiling.as_millis() as u64);
+        Duration::from_millis(millis)
+    }
+}
+
+struct BreakerState {
+    consecutive_failures: u32,
+    opened_at: Option<Instant>,
+}
+
+/// Stops sending requests to a provider after repeated failures.
+///
+/// Once `failure_threshold` consecutive requests have failed the breaker opens and
+/// every request is rejected immediately. After `cooldown` a single trial request is
+/// let through; its outcome closes the breaker again or restarts the cooldown.
+pub struct CircuitBreaker {
+    state: Mutex<BreakerState>,
+    failure_threshold: u32,
+    cooldown: Duration,
+}
+
+impl CircuitBreaker {
+    pub fn new(failure_threshold: u32, cooldown: Duration) -> Self {
+        Self {
+            state: Mutex::new(BreakerState {
+                consecutive_failures: 0,
+                opened_at: None,
+            }),
+            failure_threshold,
+            cooldown,
+        }
+    }
+
+    fn check(&self) -> Result<(), AiError> {
+        let mut state = self.state.lock().unwrap();
+        if let Some(opened_at) = state.opened_at {
+            let elapsed = opened_at.elapsed();
+            if elapsed < self.cooldown {
+                return Err(AiError::CircuitOpen { retry_in: self.cooldown - elapsed });
+            }
+            // Half-open: let this request through as a trial, and hold off the others
+            debug!("Circuit breaker half-open, sending trial request");
+            state.opened_at = Some(Instant::now());
+        }
+        Ok(())
+    }
+
+    fn record_success(&self) {
+        let mut state = self.state.lock().unwrap();
+        if state.opened_at.is_some() {
+            warn!("Circuit breaker closed after successful trial request");
+        }
+        state.consecutive_failures = 0;
+        state.opened_at = None;
+    }
+
+    fn record_failure(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.consecutive_failures += 1;
+        if state.consecutive_failures >= self.failure_threshold {
+            if state.opened_at.is_none() {
+                error!("Circuit breaker opened after {} consecutive failures", state.consecutive_failures);
+            }
+            state.opened_at = Some(Instant::now());
+        }
+    }
+}
+
+/// HTTP client shared by the AI providers, adding retries and a circuit breaker.
+pub struct ResilientHttp {
+    client: Client,
+    retry: RetryPolicy,
+    breaker: CircuitBreaker,
+}
+
+impl ResilientHttp {
+    pub fn new(retry: RetryPolicy, breaker: CircuitBreaker) -> Self {
+        Self {
+            client: Client::new(),
+            retry,
+            breaker,
+        }
+    }
+
+    /// Sends the request produced by `build`, retrying rate limits, server errors,
+    /// timeouts and network failures. Only successful responses are returned.
+    pub async fn send<F>(&self, build: F) -> Result<Response, AiError>
+    where
+        F: Fn(&Client) -> RequestBuilder,
+    {
+        self.breaker.check()?;
+
+        let mut attempt = 0;
+        loop {
+            let error = match build(&self.client).send().await {
+                Ok(response) if response.status().is_success() => {
+                    self.breaker.record_success();
+                    return Ok(response);
+                }
+                Ok(response) => Self::classify(response).await,
+                Err(e) => AiError::from_reqwest(e),
+            };
+
+            if !error.is_retryable() {
+                // The provider is reachable; the request itself was bad
+                self.breaker.record_success();
+                return Err(error);
+            }
+
+            let delay = match &error {
+                AiError::RateLimited { retry_after: Some(retry_after) } => {
+                    if *retry_after > self.retry.max_delay {
+                        warn!("Provider asked to wait {:?}, not retrying", retry_after);
+                        self.breaker.record_failure();
+                        return Err(error);
+                    }
+                    *retry_after
+                }
+                _ => self.retry.backoff(attempt),
+            };
+
+            if attempt >= self.retry.max_retries {
+                error!("AI request failed after {} retries: {}", attempt, error);
+                self.breaker.record_failure();
+                return Err(error);
+            }
+
+            warn!("AI request failed ({}), retrying in {:?}", error, delay);
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+
+    /// Reads the body of the next chunk of a streamed response.
+    pub async fn next_chunk(response: &mut Response) -> Result<Option<Vec<u8>>, AiError> {
+        response.chunk().await
+            .map(|chunk| chunk.map(|bytes| bytes.to_vec()))
+            .map_err(AiError::from_reqwest)
+    }
+
+    async fn classify(response: Response) -> AiError {
+        let status = response.status();
+        let header_delay = response.headers()
+            .get(reqwest::header::RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.trim().parse::<u64>().ok())
+            .map(Duration::from_secs);
+        let body = response.text().await.unwrap_or_default();
+
+        if status == StatusCode::TOO_MANY_REQUESTS {
+            let retry_after = header_delay.or_else(|| Self::body_retry_delay(&body));
+            AiError::RateLimited { retry_after }
+        } else if status.is_server_error() {
+            AiError::Server { status: status.as_u16(), body }
+        } else {
+            AiError::Client { status: status.as_u16(), body }
+        }
+    }
+
+    /// Gemini reports the retry delay in a `google.rpc.RetryInfo` error detail, e.g. `"retryDelay": "17s"`.
+    fn body_retry_delay(body: &str) -> Option<Duration> {
+        let json: Value = serde_json::from_str(body).ok()?;
+        json["error"]["details"]
+            .as_array()?
+            .iter()
+            .find_map(|detail| detail["retryDelay"].as_str())
+            .and_then(|delay| delay.trim_end_matches('s').parse::<f64>().ok())
+            .map(Duration::from_secs_f64)
+    }
+}
@@ -1,8 +1,23 @@
-use dashmap::DashMap;
-use serenity::model::id::{ChannelId, UserId};
-use std::sync::Arc;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::Path;
+use tracing::{info, warn};
 
-#[derive(Debug, Clone)]
+/// Built-in patterns, which are also the format expected at `INTENT_PATTERNS_PATH`.
+const DEFAULT_PATTERNS: &str = include_str!("intents.json");
+
+/// Confidence a message needs before the bot answers it locally, unless configured otherwise.
+pub const DEFAULT_MIN_CONFIDENCE: f32 = 0.6;
+
+/// Longest word sequence scored as a unit. A matched n-gram of `n` words weighs
+/// `n`, so a pattern's word order counts as well as its words.
+const MAX_NGRAM: usize = 3;
+
+/// Credit given to a word that only matches a pattern word with a typo.
+const FUZZY_SIMILARITY: f32 = 0.8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum Intent {
     StopConversation,
     StartConversation,
@@ -18,50 +33,292 @@ pub enum Intent {
     AskAvatar,
 }
 
+impl Intent {
+    /// Whether the bot answers this intent itself, without calling the model.
+    pub fn is_local(&self) -> bool {
+        matches!(
+            self,
+            Intent::CheckPing
+                | Intent::CheckServerInfo
+                | Intent::CheckMemberCount
+                | Intent::AskUsername
+                | Intent::AskNickname
+                | Intent::AskUserId
+                | Intent::AskAvatar
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct IntentScore {
+    pub intent: Intent,
+    pub confidence: f32,
+}
+
+#[derive(Deserialize)]
+struct PatternFile {
+    #[serde(default = "fuzzy_default")]
+    fuzzy: bool,
+    #[serde(default)]
+    filler: Vec<String>,
+    intents: Vec<IntentPatterns>,
+}
+
+fn fuzzy_default() -> bool {
+    true
+}
+
+#[derive(Deserialize)]
+struct IntentPatterns {
+    intent: Intent,
+    patterns: Vec<String>,
+}
+
+/// A pattern reduced to its meaningful words.
+struct Pattern {
+    intent: Intent,
+    words: Vec<String>,
+}
+
+/// Scores messages against phrase patterns for common requests, some of which
+/// the bot answers locally instead of asking the model.
+///
+/// Text is split into words, and filler words ("can you tell me", "what's
+/// the") and the bot's name and aliases are set aside. Words that make a request specific,
+/// like the "my" in "my name", are not filler. A message's confidence for a
+/// pattern is how much of the pattern it contains, weighted by n-grams, times
+/// how much of the message the pattern explains. "hey axis, what's the ping?"
+/// therefore scores 1.0 for ping, while a longer question that merely
+/// mentions ping scores low and goes to the model.
 pub struct IntentMatcher {
-    pub patterns: Vec<(Vec<&'static str>, Intent)>,
+    patterns: Vec<Pattern>,
+    filler: HashSet<String>,
+    fuzzy: bool,
 }
 
 impl IntentMatcher {
-    pub fn new() -> Self {
-        Self {
-            patterns: vec![
-                (vec!["stop", "goodbye", "bye", "that's all", "nevermind", "done", "exit", "quit", "leave"], Intent::StopConversation),
-                (vec!["hey", "hi", "hello", "yo", "sup", "help", "assist"], Intent::StartConversation),
-                (vec!["how do i", "can you help", "what is", "explain", "show me", "teach me"], Intent::AskForHelp),
-                (vec!["thanks", "thank you", "thx", "ty", "appreciated"], Intent::ThankYou),
-                (vec!["what is the ping", "check ping", "ping", "latency", "bot ping", "what's the ping"], Intent::CheckPing),
-                (vec!["server info", "serverinfo", "guild info", "about this server", "server details"], Intent::CheckServerInfo),
-                (vec!["member count", "how many members", "membercount", "total members"], Intent::CheckMemberCount),
-                (vec!["what is my username", "my username", "what's my username", "my name"], Intent::AskUsername),
-                (vec!["what is my nickname", "my nickname", "what's my nickname", "my nick"], Intent::AskNickname),
-                (vec!["what is my id", "my user id", "what's my id", "my userid"], Intent::AskUserId),
-                (vec!["what is my bio", "my bio", "what's my bio", "my about me"], Intent::AskBio),
-                (vec!["my avatar", "what's my avatar", "my profile picture", "my pfp"], Intent::AskAvatar),
-            ],
+    /// Loads patterns from `path`, falling back to the built-in patterns when no
+    /// path is set or the file cannot be read.
+    pub fn load(path: Option<&Path>) -> Self {
+        let Some(path) = path else {
+            return Self::builtin();
+        };
+
+        let parsed = std::fs::read_to_string(path)
+            .map_err(|e| e.to_string())
+            .and_then(|json| Self::from_json(&json).map_err(|e| e.to_string()));
+        match parsed {
+            Ok(matcher) => {
+                info!("Loaded {} intent patterns from {}", matcher.patterns.len(), path.display());
+                matcher
+            }
+            Err(e) => {
+                warn!("Using built-in intent patterns, {} is unusable: {}", path.display(), e);
+                Self::builtin()
+            }
         }
     }
 
-    pub fn detect_intent(&self, content: &str) -> Option<Intent> {
-        let content_lower = content.to_lowercase();
-        
-        for (patterns, intent) in &self.patterns {
-            if patterns.iter().any(|&pattern| content_lower.contains(pattern)) {
-                return Some(intent.clone());
+    fn builtin() -> Self {
+        Self::from_json(DEFAULT_PATTERNS).expect("built-in intent patterns are valid")
+    }
+
+    fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        let file: PatternFile = serde_json::from_str(json)?;
+        let filler: HashSet<String> = file.filler.iter().map(|word| word.to_lowercase()).collect();
+
+        // Patterns made only of filler words could never match, so they are dropped
+        let patterns = file.intents.into_iter()
+            .flat_map(|entry| {
+                let intent = entry.intent;
+                let filler = &filler;
+                entry.patterns.into_iter().map(move |pattern| Pattern {
+                    intent,
+                    words: tokenize(&pattern).into_iter().filter(|word| !filler.contains(word)).collect(),
+                })
+            })
+            .filter(|pattern| !pattern.words.is_empty())
+            .collect();
+
+        Ok(Self { patterns, filler, fuzzy: file.fuzzy })
+    }
+
+    /// Intents matching `content`, best first, ignoring the bot's `aliases`.
+    /// Each intent appears once, with the confidence of its best pattern.
+    pub fn classify(&self, content: &str, aliases: &[String]) -> Vec<IntentScore> {
+        let words: Vec<String> = without_aliases(tokenize(content), aliases)
+            .into_iter()
+            .filter(|word| !self.filler.contains(word))
+            .collect();
+        if words.is_empty() {
+            return Vec::new();
+        }
+
+        let mut scores: Vec<IntentScore> = Vec::new();
+        for pattern in &self.patterns {
+            let confidence = self.score(&pattern.words, &words);
+            if confidence <= 0.0 {
+                continue;
+            }
+            match scores.iter_mut().find(|score| score.intent == pattern.intent) {
+                Some(score) => score.confidence = score.confidence.max(confidence),
+                None => scores.push(IntentScore { intent: pattern.intent, confidence }),
             }
         }
-        
-        None
+
+        // Stable, so ties keep the order of the pattern file
+        scores.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+        scores
     }
 
-    pub fn should_stop_conversation(&self, content: &str, user_id: UserId, channel_id: ChannelId, active_conversations: &Arc<DashMap<ChannelId, UserId>>) -> bool {
-        if let Some(active_user) = active_conversations.get(&channel_id) {
-            if *active_user.value() == user_id {
-                if let Some(Intent::StopConversation) = self.detect_intent(content) {
-                    return true;
-                }
+    /// The highest ranked intent for `content`, if any.
+    pub fn best(&self, content: &str, aliases: &[String]) -> Option<IntentScore> {
+        self.classify(content, aliases).into_iter().next()
+    }
+
+    /// Share of the pattern's n-grams found in the message, times the share of
+    /// the message's words the pattern accounts for.
+    fn score(&self, pattern: &[String], words: &[String]) -> f32 {
+        let mut matched = 0.0;
+        let mut total = 0.0;
+        for n in 1..=MAX_NGRAM.min(pattern.len()) {
+            for ngram in pattern.windows(n) {
+                let best = words.windows(n)
+                    .map(|window| {
+                        ngram.iter().zip(window)
+                            .map(|(expected, word)| self.similarity(expected, word))
+                            .fold(1.0, f32::min)
+                    })
+                    .fold(0.0, f32::max);
+                matched += n as f32 * best;
+                total += n as f32;
             }
         }
-        false
+        if matched == 0.0 {
+            return 0.0;
+        }
+
+        let covered: f32 = words.iter()
+            .map(|word| pattern.iter().map(|expected| self.similarity(expected, word)).fold(0.0, f32::max))
+            .sum();
+        (matched / total) * (covered / words.len() as f32)
+    }
+
+    /// 1.0 for the same word, `FUZZY_SIMILARITY` for a likely typo of it and 0.0
+    /// otherwise. Short words must match exactly, so "ty" never matches "to".
+    fn similarity(&self, expected: &str, word: &str) -> f32 {
+        if expected == word {
+            return 1.0;
+        }
+        if !self.fuzzy {
+            return 0.0;
+        }
+
+        let allowed = match expected.chars().count().min(word.chars().count()) {
+            0..=3 => 0,
+            4..=7 => 1,
+            _ => 2,
+        };
+        if allowed > 0 && edit_distance(expected, word) <= allowed {
+            FUZZY_SIMILARITY
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Lowercase words of `text`, without Discord mentions and custom emoji.
+fn tokenize(text: &str) -> Vec<String> {
+    text.split_whitespace()
+        .filter(|chunk| !(chunk.starts_with('<') && chunk.ends_with('>')))
+        .flat_map(|chunk| chunk.split(|c: char| !(c.is_alphanumeric() || c == '\'' || c == '’')))
+        .map(|word| word.trim_matches(|c| c == '\'' || c == '’').replace('’', "'").to_lowercase())
+        .filter(|word| !word.is_empty())
+        .collect()
+}
+
+/// `words` without the word sequences that spell out one of `aliases`, so a
+/// multi-word alias like "axis bot" is dropped as a whole.
+fn without_aliases(words: Vec<String>, aliases: &[String]) -> Vec<String> {
+    let aliases: Vec<Vec<String>> = aliases.iter()
+        .map(|alias| tokenize(alias))
+        .filter(|alias| !alias.is_empty())
+        .collect();
+
+    let mut kept = Vec::new();
+    let mut index = 0;
+    while index < words.len() {
+        match aliases.iter().find(|alias| words[index..].starts_with(alias)) {
+            Some(alias) => index += alias.len(),
+            None => {
+                kept.push(words[index].clone());
+                index += 1;
+            }
+        }
+    }
+    kept
+}
+
+/// Edits needed to turn `a` into `b`, counting a swap of adjacent letters as one.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut rows = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in rows.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in rows[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut distance = (rows[i - 1][j] + 1)
+                .min(rows[i][j - 1] + 1)
+                .min(rows[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(rows[i - 2][j - 2] + 1);
+            }
+            rows[i][j] = distance;
+        }
+    }
+    rows[a.len()][b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Labeled messages, one per line as `label<TAB>message`. `none` marks
+    /// messages that must not be confidently matched to any intent.
+    const CORPUS: &str = include_str!("intent_corpus.tsv");
+
+    fn label(intent: Intent) -> String {
+        serde_json::to_value(intent).ok()
+            .and_then(|value| value.as_str().map(str::to_string))
+            .unwrap_or_default()
+    }
+
+    #[test]
+    fn classifies_labeled_corpus() {
+        let matcher = IntentMatcher::builtin();
+        let aliases = ["axis".to_string(), "ax".to_string(), "axis bot".to_string()];
+        let mut failures = Vec::new();
+        let mut cases = 0;
+
+        for line in CORPUS.lines().filter(|line| !line.trim().is_empty() && !line.starts_with('#')) {
+            let (expected, message) = line.split_once('\t').expect("corpus lines are label<TAB>message");
+            let best = matcher.best(message, &aliases)
+                .filter(|score| score.confidence >= DEFAULT_MIN_CONFIDENCE);
+            let actual = best.map_or("none".to_string(), |score| label(score.intent));
+            if actual != expected {
+                failures.push(format!("{:?}: expected {}, got {} ({:?})", message, expected, actual, matcher.classify(message, &aliases)));
+            }
+            cases += 1;
+        }
+
+        assert!(cases > 0, "corpus is empty");
+        assert!(failures.is_empty(), "{} of {} corpus messages misclassified:\n{}", failures.len(), cases, failures.join("\n"));
     }
 }
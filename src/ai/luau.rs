@@ -0,0 +1,87 @@
+use full_moon::ast::LuaVersion;
+
+use super::postprocess;
+
+/// A `lua`/`luau` block from an answer that failed to parse.
+#[derive(Debug, Clone)]
+pub struct SyntaxError {
+    /// 1-based position of the block among the answer's Luau blocks.
+    pub block: usize,
+    pub line: usize,
+    pub message: String,
+}
+
+/// Outcome of checking the Luau code in an answer.
+#[derive(Debug, Clone)]
+pub enum Validation {
+    /// The answer has no Luau code blocks.
+    NoCode,
+    Valid,
+    Invalid(Vec<SyntaxError>),
+}
+
+fn parse_errors(code: &str) -> Vec<(usize, String)> {
+    let result = full_moon::parse_fallible(code, LuaVersion::luau());
+    result.errors()
+        .iter()
+        .map(|error| match error {
+            full_moon::Error::AstError(error) => (
+                error.range().0.line(),
+                format!("{} (near `{}`)", error.error_message(), error.token()),
+            ),
+            full_moon::Error::TokenizerError(error) => (error.position().line(), error.error().to_string()),
+        })
+        .collect()
+}
+
+/// Parses every Luau block in `response`, keeping the first error of each block
+/// since later ones are usually knock-on effects.
+pub fn validate(response: &str) -> Validation {
+    let blocks: Vec<String> = postprocess::code_blocks(response)
+        .into_iter()
+        .filter(|block| block.is_luau())
+        .map(|block| block.code)
+        .collect();
+    if blocks.is_empty() {
+        return Validation::NoCode;
+    }
+
+    let errors: Vec<SyntaxError> = blocks.iter()
+        .enumerate()
+        .filter_map(|(index, code)| {
+            let (line, message) = parse_errors(code).into_iter().next()?;
+            Some(SyntaxError { block: index + 1, line, message })
+        })
+        .collect();
+
+    if errors.is_empty() { Validation::Valid } else { Validation::Invalid(errors) }
+}
+
+/// Follow-up asking the model to fix the blocks that did not parse.
+pub fn correction_prompt(errors: &[SyntaxError]) -> String {
+    let details = errors.iter()
+        .map(|error| format!("- Code block {}, line {}: {}", error.block, error.line, error.message))
+        .collect::<Vec<_>>()
+        .join("\n");
+    format!(
+        "Some Luau code blocks in your previous answer do not parse:\n{}\n\n\
+        Reply with your complete answer again, with the code fixed so it is valid Luau. \
+        Do not mention this correction.",
+        details
+    )
+}
+
+/// Appends a small note saying whether the answer's Luau code parsed.
+pub fn tag(response: &str, validation: &Validation) -> String {
+    match validation {
+        Validation::NoCode => response.to_string(),
+        Validation::Valid => format!("{}\n-# ✅ Luau syntax validated", response.trim_end()),
+        Validation::Invalid(errors) => {
+            let error = &errors[0];
+            format!(
+                "{}\n-# ⚠️ Luau syntax check failed (block {}, line {}), double-check this code",
+                response.trim_end(), error.block, error.line
+            )
+        }
+    }
+}
@@ -1,156 +1,370 @@
-use reqwest::Client;
-use serde_json::{json, Value};
-use anyhow::{Result, Context};
-use dashmap::DashMap;
-use serenity::model::id::{ChannelId, UserId, GuildId};
+use anyhow::Result;
+use serenity::model::id::{GuildId, MessageId};
 use serenity::model::prelude::User;
 use std::sync::Arc;
-use tracing::{error, debug, info};
+use tokio::sync::mpsc::UnboundedSender;
+use tracing::{debug, info, warn};
 
+pub mod attachments;
+pub mod cache;
+pub mod code_actions;
+pub mod history;
+pub mod http;
 pub mod intents;
+pub mod luau;
+pub mod persona;
+pub mod postprocess;
+pub mod providers;
+pub mod reply_chain;
+pub mod safety;
+pub mod splitter;
+pub mod summarize;
+pub mod tools;
+pub mod traceback;
+pub mod triggers;
+
+use crate::config::Config;
+use crate::guild_settings::{GuildSettings, GuildSettingsStore};
+use crate::rag::{self, DocIndex};
+use crate::usage::{Usage, UsageTracker};
+use cache::ResponseCache;
+use history::{Role, Turn};
+use providers::{ChatMessage, ChatRequest, FinishReason, LlmProvider, Part, ToolResult};
+
+/// Upper bound on model/function round trips for a single answer.
+const MAX_TOOL_ROUNDS: usize = 4;
+
+/// How many times an answer cut off at the token limit is continued.
+const MAX_CONTINUATIONS: usize = 2;
+
+const CONTINUE_PROMPT: &str = "Your previous answer was cut off. Continue exactly where it stopped, \
+    without repeating anything or adding an introduction.";
+
+/// How much an answer should cover, chosen with `/ask detail:`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Detail {
+    Brief,
+    Thorough,
+}
+
+impl Detail {
+    pub fn from_key(key: &str) -> Option<Self> {
+        match key {
+            "brief" => Some(Detail::Brief),
+            "thorough" => Some(Detail::Thorough),
+            _ => None,
+        }
+    }
+
+    fn instruction(&self) -> &'static str {
+        match self {
+            Detail::Brief => "ANSWER LENGTH: Be brief. Answer in a few sentences with at most one short code example.",
+            Detail::Thorough => "ANSWER LENGTH: Be thorough. Explain the reasoning, cover edge cases and common mistakes, \
+                and include complete code where it helps.",
+        }
+    }
+
+    fn max_output_tokens(&self) -> u32 {
+        match self {
+            Detail::Brief => 400,
+            Detail::Thorough => 2000,
+        }
+    }
+}
+
+/// Per-request overrides of how the model answers.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AnswerOptions {
+    pub temperature: Option<f32>,
+    pub detail: Option<Detail>,
+}
+
+/// A finished answer from [`AiClient::run`].
+struct Completion {
+    text: String,
+    /// Whether live Discord data was looked up, which makes the answer unsafe to reuse.
+    used_tools: bool,
+}
+
+/// An answer and how its Luau code fared. The note on the code is only added
+/// when the answer is posted, so history, the cache and feedback keep the
+/// model's own words.
+#[derive(Debug, Clone)]
+pub struct Answer {
+    pub text: String,
+    pub validation: luau::Validation,
+}
+
+impl Answer {
+    /// The text to post on Discord, with the Luau validation note.
+    pub fn tagged(&self) -> String {
+        luau::tag(&self.text, &self.validation)
+    }
+}
 
 #[derive(Clone)]
-pub struct GeminiClient {
-    client: Client,
-    api_key: String,
+pub struct AiClient {
+    provider: Arc<dyn LlmProvider>,
+    usage: Arc<UsageTracker>,
+    settings: Arc<GuildSettingsStore>,
+    docs: Arc<DocIndex>,
+    cache: Arc<ResponseCache>,
+    /// Documentation passages quoted per question; zero disables retrieval.
+    docs_top_k: usize,
+    bot_name: String,
+    tools_enabled: bool,
+    /// How many times an answer whose Luau does not parse is sent back for fixing.
+    luau_max_corrections: usize,
 }
 
-impl GeminiClient {
-    pub fn new(api_key: String) -> Self {
-        info!("Initializing Gemini AI client");
+impl AiClient {
+    pub fn new(
+        config: &Config,
+        usage: Arc<UsageTracker>,
+        settings: Arc<GuildSettingsStore>,
+        docs: Arc<DocIndex>,
+        cache: Arc<ResponseCache>,
+    ) -> Self {
+        let provider = providers::from_config(config);
+        info!("Initializing AI client with provider {} ({})", provider.name(), provider.model());
         Self {
-            client: Client::new(),
-            api_key,
+            provider,
+            usage,
+            settings,
+            docs,
+            cache,
+            docs_top_k: config.rag_top_k,
+            bot_name: config.bot_name.clone(),
+            tools_enabled: config.ai_tools_enabled,
+            luau_max_corrections: config.luau_max_corrections,
+        }
+    }
+
+    /// Model answering requests, as reported by the provider.
+    pub fn model(&self) -> &str {
+        self.provider.model()
+    }
+
+    fn persona_template(settings: &GuildSettings) -> &str {
+        settings.persona.as_deref().unwrap_or(persona::DEFAULT_PERSONA)
+    }
+
+    fn build_request(&self, input: ChatMessage, history: &[Turn], user: &User, settings: &GuildSettings, options: AnswerOptions) -> ChatRequest {
+        let template = Self::persona_template(settings);
+        let mut system_prompt = persona::render(template, &self.bot_name, user);
+
+        if let Some(detail) = options.detail {
+            system_prompt.push_str("\n\n");
+            system_prompt.push_str(detail.instruction());
+        }
+
+        if let Some(context) = rag::context_for(&self.docs, &input.text(), self.docs_top_k) {
+            system_prompt.push_str("\n\n");
+            system_prompt.push_str(&context);
+        }
+
+        let mut messages: Vec<ChatMessage> = history.iter().cloned().map(ChatMessage::from).collect();
+        messages.push(input);
+
+        ChatRequest {
+            system: system_prompt,
+            messages,
+            tools: if self.tools_enabled { tools::declarations() } else { Vec::new() },
+            temperature: options.temperature.unwrap_or(0.3),
+            max_output_tokens: options.detail.map_or(1000, |detail| detail.max_output_tokens()),
+            safety: settings.safety,
+            json_output: false,
         }
     }
 
-    async fn get_user_info(&self, user: &User, guild_id: Option<GuildId>, ctx: &serenity::prelude::Context) -> String {
-        let mut user_info = format!(
-            "Username: {}\nUser ID: {}\nDisplay Name: {}",
-            user.tag(),
-            user.id,
-            user.global_name.as_ref().unwrap_or(&user.name)
-        );
+    /// Sends `request` to the provider, running any functions the model calls and
+    /// feeding their results back until it produces a final answer. Answers cut off
+    /// at the token limit are continued up to [`MAX_CONTINUATIONS`] times.
+    async fn run(&self, mut request: ChatRequest, user: &User, guild_id: Option<GuildId>, ctx: &serenity::prelude::Context, chunks: Option<&UnboundedSender<String>>) -> Result<Completion> {
+        let mut usage = Usage::default();
+        let mut answer = String::new();
+        let mut tool_rounds = 0;
+        let mut continuations = 0;
+
+        loop {
+            if tool_rounds == MAX_TOOL_ROUNDS {
+                // Out of rounds: make the model answer with what it has
+                request.tools.clear();
+            }
+
+            let response = match chunks {
+                Some(chunks) => self.provider.generate_stream(&request, chunks.clone()).await,
+                None => self.provider.generate(&request).await,
+            };
+            let response = match response {
+                Ok(response) => response,
+                Err(e) => {
+                    self.usage.record(user.id, guild_id, usage).await;
+                    return Err(e);
+                }
+            };
+            usage += response.usage;
+
+            if !response.tool_calls.is_empty() {
+                tool_rounds += 1;
+                debug!("Model requested {} function calls in round {}", response.tool_calls.len(), tool_rounds);
+
+                let mut call_parts = Vec::new();
+                if !response.text.is_empty() {
+                    call_parts.push(Part::Text(response.text.clone()));
+                }
+                let mut result_parts = Vec::new();
+                for call in &response.tool_calls {
+                    let content = tools::execute(ctx, call, user, guild_id).await;
+                    call_parts.push(Part::ToolCall(call.clone()));
+                    result_parts.push(Part::ToolResult(ToolResult {
+                        call_id: call.id.clone(),
+                        name: call.name.clone(),
+                        content,
+                    }));
+                }
+
+                request.messages.push(ChatMessage { role: Role::Model, parts: call_parts });
+                request.messages.push(ChatMessage { role: Role::User, parts: result_parts });
+                continue;
+            }
+
+            answer.push_str(&response.text);
+
+            if response.finish_reason == FinishReason::MaxTokens && continuations < MAX_CONTINUATIONS {
+                continuations += 1;
+                debug!("Answer hit the token limit, continuing ({}/{})", continuations, MAX_CONTINUATIONS);
+                request.messages.push(ChatMessage { role: Role::Model, parts: vec![Part::Text(response.text)] });
+                request.messages.push(ChatMessage::user(CONTINUE_PROMPT));
+                continue;
+            }
 
-        // Get avatar URL
-        if let Some(avatar_url) = user.avatar_url() {
-            user_info.push_str(&format!("\nAvatar: {}", avatar_url));
+            self.usage.record(user.id, guild_id, usage).await;
+            return Ok(Completion { text: answer, used_tools: tool_rounds > 0 });
         }
+    }
+
+    /// Asks the model to fix answers whose Luau code blocks do not parse, at most
+    /// `luau_max_corrections` times. Returns the final answer and whether its code parsed.
+    async fn validate_luau(&self, mut request: ChatRequest, mut completion: Completion, user: &User, guild_id: Option<GuildId>, ctx: &serenity::prelude::Context) -> (Completion, luau::Validation) {
+        let mut validation = luau::validate(&completion.text);
+        let mut corrections = 0;
+
+        while let luau::Validation::Invalid(errors) = &validation {
+            if corrections == self.luau_max_corrections {
+                break;
+            }
+            corrections += 1;
+            debug!("{} Luau blocks do not parse, asking for a correction ({}/{})", errors.len(), corrections, self.luau_max_corrections);
 
-        // Get nickname and member info if in a guild
-        if let Some(guild_id) = guild_id {
-            match guild_id.member(&ctx.http, user.id).await {
-                Ok(member) => {
-                    if let Some(nick) = &member.nick {
-                        user_info.push_str(&format!("\nNickname: {}", nick));
-                    }
-                    
-                    // Note: User bio/profile info is not easily accessible via standard bot APIs
-                    // This would require special Discord permissions and may not work for most bots
-                },
-                Err(e) => debug!("Could not fetch member info: {}", e)
+            request.messages.push(ChatMessage { role: Role::Model, parts: vec![Part::Text(completion.text.clone())] });
+            request.messages.push(ChatMessage::user(luau::correction_prompt(errors)));
+            match self.run(request.clone(), user, guild_id, ctx, None).await {
+                Ok(corrected) => {
+                    completion = Completion {
+                        text: corrected.text,
+                        used_tools: completion.used_tools || corrected.used_tools,
+                    };
+                    validation = luau::validate(&completion.text);
+                }
+                Err(e) => {
+                    warn!("Failed to get a Luau correction, keeping the original answer: {}", e);
+                    break;
+                }
             }
         }
 
-        user_info
-    }
-
-    pub async fn generate_response(&self, prompt: &str, user: &User, guild_id: Option<GuildId>, ctx: &serenity::prelude::Context) -> Result<String> {
-        let url = format!(
-            "https://generativelanguage.googleapis.com/v1beta/models/gemini-1.5-flash-latest:generateContent?key={}",
-            self.api_key
-        );
-
-        let user_info = self.get_user_info(user, guild_id, ctx).await;
-
-        let system_prompt = format!(
-            "You are Axis, a professional Discord bot designed specifically for Roblox development assistance. \
-            Your role is to provide expert guidance on Roblox Studio, Luau scripting, game development patterns, \
-            optimization techniques, and development best practices.\n\n\
-            IMPORTANT GUIDELINES:\n\
-            - Maintain a professional, serious tone at all times\n\
-            - Never use emojis, especially happy or cheerful ones\n\
-            - Be direct, clear, and technical in your responses\n\
-            - Focus on providing accurate, actionable information\n\
-            - Keep responses under 2000 characters due to Discord limits\n\
-            - When providing code examples, use proper Luau syntax\n\
-            - If you don't know something, state it directly rather than guessing\n\
-            - Address the user by their username when appropriate\n\
-            - You can reference user information like their avatar, nickname, and user ID when relevant\n\n\
-            Current user information:\n{}\n\n\
-            User message: {}",
-            user_info, prompt
-        );
-
-        let payload = json!({
-            "contents": [{
-                "parts": [{
-                    "text": system_prompt
-                }]
-            }],
-            "generationConfig": {
-                "temperature": 0.3,
-                "topK": 20,  
-                "topP": 0.8,
-                "maxOutputTokens": 1000,
-            },
-            "safetySettings": [
-                {
-                    "category": "HARM_CATEGORY_HARASSMENT",
-                    "threshold": "BLOCK_MEDIUM_AND_ABOVE"
-                },
-                {
-                    "category": "HARM_CATEGORY_HATE_SPEECH",
-                    "threshold": "BLOCK_MEDIUM_AND_ABOVE"
-                },
-                {
-                    "category": "HARM_CATEGORY_SEXUALLY_EXPLICIT",
-                    "threshold": "BLOCK_MEDIUM_AND_ABOVE"
-                },
-                {
-                    "category": "HARM_CATEGORY_DANGEROUS_CONTENT",
-                    "threshold": "BLOCK_MEDIUM_AND_ABOVE"
+        (completion, validation)
+    }
+
+    /// Answers `input`, reusing a cached answer when the same standalone question
+    /// was recently asked under the same persona template, detail level and
+    /// safety settings.
+    #[allow(clippy::too_many_arguments)]
+    async fn respond(&self, input: ChatMessage, history: &[Turn], user: &User, guild_id: Option<GuildId>, ctx: &serenity::prelude::Context, chunks: Option<&UnboundedSender<String>>, options: AnswerOptions) -> Result<Answer> {
+        let settings = self.settings.get(guild_id).await;
+        let template = Self::persona_template(&settings);
+
+        // Follow-ups and attachments depend on more than the question text, a
+        // custom temperature asks for a fresh answer, and a custom persona that
+        // addresses the asker by name answers each user differently
+        let cacheable = self.cache.is_enabled()
+            && history.is_empty()
+            && input.parts.len() == 1
+            && options.temperature.is_none()
+            && !(settings.persona.is_some() && persona::is_personal(template));
+        let cache_key = cacheable
+            .then(|| {
+                // An answer allowed under one server's safety thresholds may be blocked under another's
+                let mut persona = template.to_string();
+                if let Some(detail) = options.detail {
+                    persona.push_str(detail.instruction());
                 }
-            ]
-        });
+                persona.push_str(&format!("\u{0}{:?}", settings.safety));
+                ResponseCache::key(&input.text(), &self.bot_name, &persona)
+            })
+            .flatten();
 
-        debug!("Sending request to Gemini API for response generation");
-        
-        let response = self.client
-            .post(&url)
-            .json(&payload)
-            .timeout(std::time::Duration::from_secs(15))
-            .send()
-            .await
-            .context("Failed to send request to Gemini API")?;
-
-        let status = response.status();
-        if !status.is_success() {
-            let error_text = response.text().await.unwrap_or_default();
-            error!("Gemini API error {}: {}", status, error_text);
-            return Err(anyhow::anyhow!("Gemini API error {}: {}", status, error_text));
+        if let Some(cached) = cache_key.as_deref().and_then(|key| self.cache.get(key)) {
+            debug!("Answering {} from the response cache", user.tag());
+            if let Some(chunks) = chunks {
+                let _ = chunks.send(cached.clone());
+            }
+            return Ok(Answer { validation: luau::validate(&cached), text: cached });
         }
 
-        let json: Value = response.json().await
-            .context("Failed to parse Gemini API response")?;
-
-        debug!("Successfully received response from Gemini API");
-
-        // Extract the response text with better error handling
-        let text = json["candidates"]
-            .get(0)
-            .and_then(|candidate| candidate["content"]["parts"].get(0))
-            .and_then(|part| part["text"].as_str())
-            .context("Invalid response structure from Gemini API")?
-            .to_string();
-
-        // Ensure Discord character limit compliance
-        if text.len() > 2000 {
-            info!("Response truncated from {} to 2000 characters", text.len());
-            Ok(format!("{}...", &text[..1997]))
-        } else {
-            Ok(text)
+        let request = self.build_request(input, history, user, &settings, options);
+        let completion = self.run(request.clone(), user, guild_id, ctx, chunks).await?;
+        let (completion, validation) = self.validate_luau(request, completion, user, guild_id, ctx).await;
+
+        if let (Some(key), false) = (cache_key, completion.used_tools) {
+            self.cache.insert(key, completion.text.clone());
         }
+        Ok(Answer { text: completion.text, validation })
+    }
+
+    pub async fn generate_response(&self, input: ChatMessage, history: &[Turn], user: &User, guild_id: Option<GuildId>, ctx: &serenity::prelude::Context) -> Result<Answer> {
+        debug!("Generating response via {} with {} history turns", self.provider.name(), history.len());
+        self.respond(input, history, user, guild_id, ctx, None, AnswerOptions::default()).await
+    }
+
+    /// Like [`generate_response`](Self::generate_response), with overrides such as
+    /// the higher temperature used to regenerate an answer.
+    pub async fn generate_response_with(&self, input: ChatMessage, history: &[Turn], options: AnswerOptions, user: &User, guild_id: Option<GuildId>, ctx: &serenity::prelude::Context) -> Result<Answer> {
+        debug!("Generating response via {} with {} history turns ({:?})", self.provider.name(), history.len(), options);
+        self.respond(input, history, user, guild_id, ctx, None, options).await
+    }
+
+    /// Answers a one-off request from a slash or context-menu command with the caller's overrides.
+    pub async fn ask(&self, input: ChatMessage, options: AnswerOptions, user: &User, guild_id: Option<GuildId>, ctx: &serenity::prelude::Context) -> Result<Answer> {
+        debug!("Answering a one-off request via {} ({:?})", self.provider.name(), options);
+        self.respond(input, &[], user, guild_id, ctx, None, options).await
+    }
+
+    /// Like [`generate_response`](Self::generate_response), but forwards text chunks
+    /// to `chunks` as the provider produces them.
+    pub async fn generate_response_stream(&self, input: ChatMessage, history: &[Turn], user: &User, guild_id: Option<GuildId>, ctx: &serenity::prelude::Context, chunks: UnboundedSender<String>) -> Result<Answer> {
+        debug!("Streaming response via {} with {} history turns", self.provider.name(), history.len());
+        self.respond(input, history, user, guild_id, ctx, Some(&chunks), AnswerOptions::default()).await
+    }
+
+    /// Summarizes a channel transcript built with [`summarize::transcript`].
+    pub async fn summarize(&self, transcript: &str, known: &[MessageId], user: &User, guild_id: Option<GuildId>) -> Result<summarize::Summary> {
+        let settings = self.settings.get(guild_id).await;
+        let request = ChatRequest {
+            system: summarize::SYSTEM_PROMPT.to_string(),
+            messages: vec![ChatMessage::user(transcript)],
+            tools: Vec::new(),
+            temperature: 0.2,
+            max_output_tokens: 1500,
+            safety: settings.safety,
+            json_output: true,
+        };
+
+        debug!("Summarizing {} characters of channel history via {}", transcript.len(), self.provider.name());
+        let response = self.provider.generate(&request).await?;
+        self.usage.record(user.id, guild_id, response.usage).await;
+        summarize::parse(&response.text, known)
     }
 
     pub fn should_stop_conversation(&self, message: &str) -> bool {
@@ -184,66 +398,4 @@ impl GeminiClient {
         debug!("Stop conversation analysis - explicit: {}, final_thanks: {}", explicit_stops, is_final_thanks);
         explicit_stops || is_final_thanks
     }
-
-    pub fn should_respond_to_message(
-        &self,
-        content: &str,
-        bot_name: &str,
-        author_id: UserId,
-        channel_id: ChannelId,
-        active_conversations: &Arc<DashMap<ChannelId, UserId>>,
-    ) -> bool {
-        // If there's an active conversation with this user in this channel, always respond
-        if let Some(active_user_id) = active_conversations.get(&channel_id) {
-            if *active_user_id == author_id {
-                debug!("Responding due to active conversation with user {}", author_id);
-                return true;
-            }
-        }
-
-        // Reliable keyword-based detection for new conversations
-        let content_lower_string = content.to_lowercase();
-        let content_lower = content_lower_string.trim();
-        let bot_name_lower = bot_name.to_lowercase();
-        
-        // Direct bot mentions and greetings (always trigger new conversations)
-        let direct_mentions = [
-            &bot_name_lower,
-            &format!("hey {}", bot_name_lower),
-            &format!("hi {}", bot_name_lower), 
-            &format!("hello {}", bot_name_lower),
-            &format!("{} help", bot_name_lower),
-            &format!("help {}", bot_name_lower),
-        ];
-
-        // Roblox/development keywords
-        let dev_keywords = [
-            "roblox", "luau", "script", "scripting", "studio", "rbx", "remote event",
-            "remote function", "datastore", "leaderstats", "gui", "screengu",
-            "local script", "server script", "game development", "rbxasset"
-        ];
-
-        // Help request patterns
-        let help_patterns = [
-            "help me", "can you help", "i need help", "how do i", "how to",
-            "what is", "explain", "show me", "teach me", "can you",
-            "do you know", "question about"
-        ];
-
-        let has_direct_mention = direct_mentions.iter().any(|&mention| 
-            content_lower.contains(mention) || content_lower == mention
-        );
-        let has_dev_keyword = dev_keywords.iter().any(|&keyword| content_lower.contains(keyword));
-        let has_help_request = help_patterns.iter().any(|&pattern| content_lower.contains(pattern));
-
-        // Always respond to direct mentions, even without dev keywords
-        let should_respond = has_direct_mention || 
-            (has_help_request && has_dev_keyword) || 
-            (content_lower.len() > 10 && has_dev_keyword && content_lower.contains("?"));
-
-        debug!("Response decision for '{}' - direct: {}, dev: {}, help: {}, result: {}", 
-               content_lower, has_direct_mention, has_dev_keyword, has_help_request, should_respond);
-        
-        should_respond
-    }
 }
@@ -0,0 +1,65 @@
+use serenity::model::prelude::User;
+
+/// Longest custom system prompt accepted from `/persona set`, in characters.
+pub const MAX_PERSONA_CHARS: usize = 4000;
+
+/// Placeholders a persona may use, with what they expand to.
+pub const TEMPLATE_VARIABLES: &[(&str, &str)] = &[
+    ("{bot_name}", "the bot's name"),
+    ("{user_info}", "username, ID and display name of the person asking"),
+    ("{username}", "username of the person asking"),
+    ("{display_name}", "display name of the person asking"),
+];
+
+/// System prompt used when a server has not set its own persona.
+pub const DEFAULT_PERSONA: &str = "You are {bot_name}, a professional Discord bot designed specifically for Roblox development assistance. \
+Your role is to provide expert guidance on Roblox Studio, Luau scripting, game development patterns, \
+optimization techniques, and development best practices.\n\n\
+IMPORTANT GUIDELINES:\n\
+- Maintain a professional, serious tone at all times\n\
+- Never use emojis, especially happy or cheerful ones\n\
+- Be direct, clear, and technical in your responses\n\
+- Focus on providing accurate, actionable information\n\
+- Keep responses focused; answers longer than 2000 characters are split across several Discord messages\n\
+- When providing code examples, use proper Luau syntax\n\
+- If you don't know something, state it directly rather than guessing\n\
+- Address the user by their username when appropriate\n\
+- Use the available functions to look up server details, member counts, user profiles and bot latency instead of guessing\n\n\
+Current user information:\n{user_info}";
+
+fn user_info(user: &User) -> String {
+    format!(
+        "Username: {}\nUser ID: {}\nDisplay Name: {}",
+        user.tag(),
+        user.id,
+        user.global_name.as_ref().unwrap_or(&user.name)
+    )
+}
+
+/// The bot name as it should read in a sentence, e.g. `axis` becomes `Axis`.
+fn display_bot_name(bot_name: &str) -> String {
+    let mut chars = bot_name.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// Expands the template variables in `template` for a request from `user`.
+pub fn render(template: &str, bot_name: &str, user: &User) -> String {
+    template
+        .replace("{bot_name}", &display_bot_name(bot_name))
+        .replace("{user_info}", &user_info(user))
+        .replace("{username}", &user.name)
+        .replace("{display_name}", user.global_name.as_ref().unwrap_or(&user.name))
+}
+
+/// Whether `template` mentions the person asking, so its answers differ per user.
+pub fn is_personal(template: &str) -> bool {
+    ["{user_info}", "{username}", "{display_name}"].iter().any(|variable| template.contains(variable))
+}
+
+/// Slash command options are single-line, so `\n` typed in a persona stands for a line break.
+pub fn normalize(input: &str) -> String {
+    input.replace("\\n", "\n").trim().to_string()
+}
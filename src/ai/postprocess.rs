@@ -0,0 +1,141 @@
+use super::splitter::DISCORD_MESSAGE_LIMIT;
+
+/// A code block pulled out of an answer to be sent as a file.
+#[derive(Debug, Clone)]
+pub struct CodeFile {
+    pub name: String,
+    pub contents: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct ProcessedResponse {
+    pub text: String,
+    pub files: Vec<CodeFile>,
+}
+
+/// A fenced code block and the language it was tagged with (empty when untagged).
+#[derive(Debug, Clone)]
+pub struct CodeBlock {
+    pub lang: String,
+    pub code: String,
+}
+
+impl CodeBlock {
+    pub fn is_luau(&self) -> bool {
+        matches!(self.lang.to_lowercase().as_str(), "lua" | "luau")
+    }
+}
+
+/// The fenced code blocks in `text`, in order. Unterminated blocks are skipped
+/// since the text may have been cut off.
+pub fn code_blocks(text: &str) -> Vec<CodeBlock> {
+    let mut blocks = Vec::new();
+    let mut block: Option<CodeBlock> = None;
+
+    for line in text.split_inclusive('\n') {
+        let fence = line.trim().strip_prefix("```");
+        match (&mut block, fence) {
+            (None, Some(lang)) => block = Some(CodeBlock { lang: lang.trim().to_string(), code: String::new() }),
+            (Some(_), Some(_)) => blocks.extend(block.take()),
+            (Some(current), None) => current.code.push_str(line),
+            (None, None) => {}
+        }
+    }
+    blocks
+}
+
+/// Longest line of code that fits in a Discord message along with its fences.
+/// The splitter never cuts code lines, so blocks with longer lines become files.
+const MAX_CODE_LINE_CHARS: usize = DISCORD_MESSAGE_LIMIT - 100;
+
+/// Whether `code` should be sent as a file: it is longer than `threshold`
+/// characters (zero disables that), or has a line too long for one message.
+fn needs_file(code: &str, threshold: usize) -> bool {
+    (threshold > 0 && code.chars().count() > threshold)
+        || code.lines().any(|line| line.chars().count() > MAX_CODE_LINE_CHARS)
+}
+
+/// Replaces fenced code blocks longer than `threshold` characters with a short
+/// pointer and returns them as files, so long scripts arrive intact instead of
+/// being spread over several messages. A `threshold` of zero disables extraction,
+/// except for blocks with lines too long to fit in a message.
+pub fn extract_code_files(response: &str, threshold: usize) -> ProcessedResponse {
+
+    let mut text = String::new();
+    let mut files: Vec<CodeFile> = Vec::new();
+    // (language tag, raw fenced lines, code lines) of the block being read
+    let mut block: Option<(String, String, String)> = None;
+
+    for line in response.split_inclusive('\n') {
+        let fence = line.trim().strip_prefix("```");
+        match (&mut block, fence) {
+            (None, Some(lang)) => {
+                block = Some((lang.trim().to_string(), line.to_string(), String::new()));
+            }
+            (Some(_), Some(_)) => {
+                let (lang, mut raw, code) = block.take().unwrap_or_default();
+                raw.push_str(line);
+                if needs_file(&code, threshold) {
+                    let name = unique_name(&files, file_name(&lang, &code, files.len() + 1));
+                    text.push_str(&format!("*(Full code attached as `{}`)*\n", name));
+                    files.push(CodeFile { name, contents: code });
+                } else {
+                    text.push_str(&raw);
+                }
+            }
+            (Some((_, raw, code)), None) => {
+                raw.push_str(line);
+                code.push_str(line);
+            }
+            (None, None) => text.push_str(line),
+        }
+    }
+
+    // An unterminated block is left in the text as-is, unless it cannot be split
+    if let Some((lang, raw, code)) = block {
+        if needs_file(&code, 0) {
+            let name = unique_name(&files, file_name(&lang, &code, files.len() + 1));
+            text.push_str(&format!("*(Full code attached as `{}`)*\n", name));
+            files.push(CodeFile { name, contents: code });
+        } else {
+            text.push_str(&raw);
+        }
+    }
+
+    ProcessedResponse { text, files }
+}
+
+/// Picks a file name for a block: a leading `-- Name.luau` style comment wins,
+/// otherwise `snippet_<n>` with an extension matching the fence language.
+fn file_name(lang: &str, code: &str, index: usize) -> String {
+    let extension = match lang.to_lowercase().as_str() {
+        "" | "lua" | "luau" => "luau".to_string(),
+        other if other.chars().all(|c| c.is_ascii_alphanumeric()) => other.to_string(),
+        _ => "txt".to_string(),
+    };
+
+    let named = code.lines()
+        .next()
+        .and_then(|first| first.trim().strip_prefix("--"))
+        .map(|comment| comment.trim().rsplit(['/', '\\', ' ']).next().unwrap_or_default().to_string())
+        .filter(|name| {
+            (name.ends_with(".lua") || name.ends_with(".luau"))
+                && name.chars().all(|c| c.is_ascii_alphanumeric() || "._-".contains(c))
+        });
+
+    match named {
+        Some(name) if extension == "luau" => name,
+        _ => format!("snippet_{}.{}", index, extension),
+    }
+}
+
+fn unique_name(files: &[CodeFile], name: String) -> String {
+    if !files.iter().any(|file| file.name == name) {
+        return name;
+    }
+    let (stem, extension) = name.rsplit_once('.').unwrap_or((&name, "luau"));
+    (2..)
+        .map(|n| format!("{}_{}.{}", stem, n, extension))
+        .find(|candidate| !files.iter().any(|file| &file.name == candidate))
+        .unwrap_or_else(|| name.clone())
+}
@@ -0,0 +1,290 @@
+use anyhow::{Context, Result};
+use reqwest::{Client, RequestBuilder};
+use serde_json::{json, Value};
+use serenity::async_trait;
+use std::time::Duration;
+use tokio::sync::mpsc::UnboundedSender;
+use tracing::{debug, error, info, warn};
+
+use super::sse::SseBuffer;
+use super::{ChatMessage, ChatRequest, ChatResponse, FinishReason, LlmProvider, Part, ToolCall};
+use crate::ai::http::{AiError, BlockReason, ResilientHttp};
+use crate::ai::safety::{HarmCategory, SafetySettings};
+use crate::usage::Usage;
+
+const DEFAULT_BASE_URL: &str = "https://generativelanguage.googleapis.com/v1beta";
+
+pub struct GeminiProvider {
+    http: ResilientHttp,
+    api_key: String,
+    model: String,
+    base_url: String,
+    timeout: Duration,
+}
+
+impl GeminiProvider {
+    pub fn new(http: ResilientHttp, api_key: String, model: String, base_url: Option<String>, timeout: Duration) -> Self {
+        info!("Initializing Gemini provider with model {}", model);
+        Self {
+            http,
+            api_key,
+            model,
+            base_url: base_url
+                .unwrap_or_else(|| DEFAULT_BASE_URL.to_string())
+                .trim_end_matches('/')
+                .to_string(),
+            timeout,
+        }
+    }
+
+    /// The key goes in a header rather than the URL, which ends up in error messages and logs.
+    fn post(&self, client: &Client, url: &str) -> RequestBuilder {
+        client.post(url).header("x-goog-api-key", &self.api_key)
+    }
+
+    fn parse_usage(json: &Value) -> Option<Usage> {
+        let metadata = json.get("usageMetadata")?;
+        Some(Usage {
+            prompt_tokens: metadata["promptTokenCount"].as_u64().unwrap_or(0),
+            output_tokens: metadata["candidatesTokenCount"].as_u64().unwrap_or(0),
+        })
+    }
+
+    fn message_to_content(message: &ChatMessage) -> Value {
+        let parts: Vec<Value> = message.parts.iter()
+            .map(|part| match part {
+                Part::Text(text) => json!({ "text": text }),
+                Part::InlineData { mime_type, data } => json!({
+                    "inline_data": { "mime_type": mime_type, "data": Part::base64_data(data) }
+                }),
+                Part::ToolCall(call) => json!({
+                    "functionCall": { "name": call.name, "args": call.args }
+                }),
+                Part::ToolResult(result) => json!({
+                    "functionResponse": {
+                        "name": result.name,
+                        "response": { "content": result.content }
+                    }
+                }),
+            })
+            .collect();
+
+        json!({
+            "role": message.role.as_gemini_role(),
+            "parts": parts
+        })
+    }
+
+    /// Collects the text and function calls of the first candidate.
+    fn parse_candidate(json: &Value, text: &mut String, tool_calls: &mut Vec<ToolCall>) {
+        let parts = json["candidates"]
+            .get(0)
+            .and_then(|candidate| candidate["content"]["parts"].as_array());
+        for part in parts.into_iter().flatten() {
+            if let Some(chunk) = part["text"].as_str() {
+                text.push_str(chunk);
+            }
+            if let Some(call) = part.get("functionCall") {
+                tool_calls.push(ToolCall {
+                    id: None,
+                    name: call["name"].as_str().unwrap_or_default().to_string(),
+                    args: call.get("args").cloned().unwrap_or_else(|| json!({})),
+                });
+            }
+        }
+    }
+
+    /// Harm categories that caused a block: the ones marked `blocked`, or failing
+    /// that, the ones rated medium or high probability.
+    fn flagged_categories(ratings: &Value) -> Vec<String> {
+        let ratings = ratings.as_array().map(Vec::as_slice).unwrap_or_default();
+        debug!("Gemini safety ratings: {:?}", ratings);
+
+        let named = |rating: &&Value| rating["category"].as_str().map(HarmCategory::label_for_api_name);
+        let blocked: Vec<String> = ratings.iter()
+            .filter(|rating| rating["blocked"].as_bool() == Some(true))
+            .filter_map(|rating| named(&rating))
+            .collect();
+        if !blocked.is_empty() {
+            return blocked;
+        }
+        ratings.iter()
+            .filter(|rating| matches!(rating["probability"].as_str(), Some("MEDIUM" | "HIGH")))
+            .filter_map(|rating| named(&rating))
+            .collect()
+    }
+
+    /// Reads `promptFeedback` and the first candidate's `finishReason`, turning
+    /// blocked prompts and withheld answers into errors. Returns `None` when the
+    /// response (or stream event) does not say why generation stopped.
+    fn finish_reason(json: &Value) -> Result<Option<FinishReason>, AiError> {
+        if let Some(reason) = json["promptFeedback"]["blockReason"].as_str() {
+            return Err(AiError::PromptBlocked {
+                reason: reason.to_string(),
+                categories: Self::flagged_categories(&json["promptFeedback"]["safetyRatings"]),
+            });
+        }
+
+        let candidate = &json["candidates"][0];
+        let Some(reason) = candidate["finishReason"].as_str() else {
+            return Ok(None);
+        };
+
+        let blocked = match reason {
+            "STOP" => return Ok(Some(FinishReason::Stop)),
+            "MAX_TOKENS" => return Ok(Some(FinishReason::MaxTokens)),
+            "SAFETY" => BlockReason::Safety,
+            "RECITATION" => BlockReason::Recitation,
+            "BLOCKLIST" | "PROHIBITED_CONTENT" | "SPII" | "IMAGE_SAFETY" => BlockReason::Other(reason.to_string()),
+            other => {
+                debug!("Gemini finished with reason {}", other);
+                return Ok(Some(FinishReason::Stop));
+            }
+        };
+
+        Err(AiError::ResponseBlocked {
+            reason: blocked,
+            categories: Self::flagged_categories(&candidate["safetyRatings"]),
+        })
+    }
+
+    fn safety_settings(settings: &SafetySettings) -> Vec<Value> {
+        HarmCategory::ALL.iter()
+            .map(|category| json!({
+                "category": category.api_name(),
+                "threshold": settings.get(*category).api_name()
+            }))
+            .collect()
+    }
+
+    fn build_payload(&self, request: &ChatRequest) -> Value {
+        let contents: Vec<Value> = request.messages.iter()
+            .map(Self::message_to_content)
+            .collect();
+
+        let mut payload = json!({
+            "systemInstruction": {
+                "parts": [{
+                    "text": request.system
+                }]
+            },
+            "contents": contents,
+            "generationConfig": {
+                "temperature": request.temperature,
+                "topK": 20,
+                "topP": 0.8,
+                "maxOutputTokens": request.max_output_tokens,
+            },
+            "safetySettings": Self::safety_settings(&request.safety)
+        });
+
+        if request.json_output {
+            payload["generationConfig"]["responseMimeType"] = json!("application/json");
+        }
+
+        if !request.tools.is_empty() {
+            let declarations: Vec<Value> = request.tools.iter()
+                .map(|tool| json!({
+                    "name": tool.name,
+                    "description": tool.description,
+                    "parameters": tool.parameters
+                }))
+                .collect();
+            payload["tools"] = json!([{ "functionDeclarations": declarations }]);
+        }
+
+        payload
+    }
+}
+
+#[async_trait]
+impl LlmProvider for GeminiProvider {
+    fn name(&self) -> &'static str {
+        "gemini"
+    }
+
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    async fn generate(&self, request: &ChatRequest) -> Result<ChatResponse> {
+        let url = format!("{}/models/{}:generateContent", self.base_url, self.model);
+
+        debug!("Sending request to Gemini API for response generation with {} messages", request.messages.len());
+
+        let payload = self.build_payload(request);
+        let response = self.http
+            .send(|client| self.post(client, &url).json(&payload).timeout(self.timeout))
+            .await
+            .inspect_err(|e| error!("Gemini API request failed: {}", e))?;
+
+        let json: Value = response.json().await
+            .map_err(|e| AiError::InvalidResponse(e.without_url().to_string()))
+            .context("Failed to parse Gemini API response")?;
+
+        debug!("Successfully received response from Gemini API");
+
+        let finish_reason = Self::finish_reason(&json)
+            .inspect_err(|e| warn!("Gemini did not answer: {}", e))?
+            .unwrap_or_default();
+
+        let mut text = String::new();
+        let mut tool_calls = Vec::new();
+        Self::parse_candidate(&json, &mut text, &mut tool_calls);
+
+        if text.is_empty() && tool_calls.is_empty() && finish_reason != FinishReason::MaxTokens {
+            return Err(AiError::EmptyResponse.into());
+        }
+
+        let usage = Self::parse_usage(&json).unwrap_or_default();
+
+        Ok(ChatResponse { text, tool_calls, usage, finish_reason })
+    }
+
+    async fn generate_stream(&self, request: &ChatRequest, chunks: UnboundedSender<String>) -> Result<ChatResponse> {
+        let url = format!("{}/models/{}:streamGenerateContent?alt=sse", self.base_url, self.model);
+
+        debug!("Opening Gemini response stream with {} messages", request.messages.len());
+
+        // The timeout covers the whole stream, so allow more time than a single request
+        let payload = self.build_payload(request);
+        let mut response = self.http
+            .send(|client| self.post(client, &url).json(&payload).timeout(self.timeout * 4))
+            .await
+            .inspect_err(|e| error!("Gemini API stream request failed: {}", e))?;
+
+        let mut events = SseBuffer::default();
+        let mut text = String::new();
+        let mut tool_calls = Vec::new();
+        let mut usage = Usage::default();
+        let mut finish_reason = FinishReason::default();
+
+        while let Some(bytes) = ResilientHttp::next_chunk(&mut response).await.context("Gemini response stream interrupted")? {
+            for event in events.push(&bytes) {
+                let json: Value = serde_json::from_str(&event)
+                    .map_err(|e| AiError::InvalidResponse(e.to_string()))
+                    .context("Failed to parse Gemini stream event")?;
+                if let Some(reason) = Self::finish_reason(&json).inspect_err(|e| warn!("Gemini stopped the stream: {}", e))? {
+                    finish_reason = reason;
+                }
+                // Every event repeats the running totals, so the last one wins
+                if let Some(event_usage) = Self::parse_usage(&json) {
+                    usage = event_usage;
+                }
+                let mut chunk = String::new();
+                Self::parse_candidate(&json, &mut chunk, &mut tool_calls);
+                if !chunk.is_empty() {
+                    text.push_str(&chunk);
+                    let _ = chunks.send(chunk);
+                }
+            }
+        }
+
+        if text.is_empty() && tool_calls.is_empty() && finish_reason != FinishReason::MaxTokens {
+            return Err(AiError::EmptyResponse.into());
+        }
+
+        debug!("Gemini stream finished with {} characters and {} function calls", text.len(), tool_calls.len());
+        Ok(ChatResponse { text, tool_calls, usage, finish_reason })
+    }
+}
@@ -0,0 +1,170 @@
+use anyhow::Result;
+use serde_json::Value;
+use serenity::async_trait;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc::UnboundedSender;
+
+use crate::ai::history::{Role, Turn};
+use crate::ai::http::{CircuitBreaker, ResilientHttp, RetryPolicy};
+use crate::ai::safety::SafetySettings;
+use crate::config::{Config, ProviderKind};
+use crate::usage::Usage;
+
+pub mod gemini;
+pub mod ollama;
+pub mod openai;
+mod sse;
+
+pub use gemini::GeminiProvider;
+pub use ollama::OllamaProvider;
+pub use openai::OpenAiProvider;
+
+/// A function the model asked the bot to run.
+#[derive(Debug, Clone)]
+pub struct ToolCall {
+    /// Call id for APIs that pair calls with results (OpenAI); `None` elsewhere.
+    pub id: Option<String>,
+    pub name: String,
+    pub args: Value,
+}
+
+/// The output of a [`ToolCall`], sent back to the model.
+#[derive(Debug, Clone)]
+pub struct ToolResult {
+    pub call_id: Option<String>,
+    pub name: String,
+    pub content: Value,
+}
+
+#[derive(Debug, Clone)]
+pub enum Part {
+    Text(String),
+    /// Binary content such as an image, sent to the model alongside the text.
+    InlineData { mime_type: String, data: Vec<u8> },
+    ToolCall(ToolCall),
+    ToolResult(ToolResult),
+}
+
+impl Part {
+    /// The inline data encoded as standard base64, as every provider expects it.
+    pub fn base64_data(data: &[u8]) -> String {
+        use base64::Engine;
+        base64::engine::general_purpose::STANDARD.encode(data)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ChatMessage {
+    pub role: Role,
+    pub parts: Vec<Part>,
+}
+
+impl ChatMessage {
+    pub fn user(text: impl Into<String>) -> Self {
+        Self { role: Role::User, parts: vec![Part::Text(text.into())] }
+    }
+
+    /// Concatenated text parts of the message.
+    pub fn text(&self) -> String {
+        self.parts.iter()
+            .filter_map(|part| match part {
+                Part::Text(text) => Some(text.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+impl From<Turn> for ChatMessage {
+    fn from(turn: Turn) -> Self {
+        Self { role: turn.role, parts: vec![Part::Text(turn.text)] }
+    }
+}
+
+/// A function the model may call, with its parameters as a JSON schema object.
+#[derive(Debug, Clone)]
+pub struct ToolDeclaration {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub parameters: Value,
+}
+
+/// A single request to a chat model, independent of the vendor API.
+#[derive(Debug, Clone)]
+pub struct ChatRequest {
+    pub system: String,
+    /// Conversation messages, ending with the user message to answer.
+    pub messages: Vec<ChatMessage>,
+    pub tools: Vec<ToolDeclaration>,
+    pub temperature: f32,
+    pub max_output_tokens: u32,
+    /// Content filter thresholds, for providers that let callers tune them (Gemini).
+    pub safety: SafetySettings,
+    /// Ask the model to answer with a single JSON object.
+    pub json_output: bool,
+}
+
+/// Why the model stopped generating. Blocked answers are reported as
+/// [`AiError`](crate::ai::http::AiError)s instead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FinishReason {
+    #[default]
+    Stop,
+    /// The answer was cut off at `max_output_tokens`.
+    MaxTokens,
+}
+
+#[derive(Debug, Clone)]
+pub struct ChatResponse {
+    pub text: String,
+    /// Functions the model wants run before it gives its final answer.
+    pub tool_calls: Vec<ToolCall>,
+    pub usage: Usage,
+    pub finish_reason: FinishReason,
+}
+
+#[async_trait]
+pub trait LlmProvider: Send + Sync {
+    /// Short vendor name used in logs.
+    fn name(&self) -> &'static str;
+
+    fn model(&self) -> &str;
+
+    async fn generate(&self, request: &ChatRequest) -> Result<ChatResponse>;
+
+    /// Generates a response while forwarding text chunks to `chunks` as they arrive.
+    ///
+    /// Backends without a streaming API fall back to a single chunk holding the whole answer.
+    async fn generate_stream(&self, request: &ChatRequest, chunks: UnboundedSender<String>) -> Result<ChatResponse> {
+        let response = self.generate(request).await?;
+        if !response.text.is_empty() {
+            let _ = chunks.send(response.text.clone());
+        }
+        Ok(response)
+    }
+}
+
+pub fn from_config(config: &Config) -> Arc<dyn LlmProvider> {
+    let api_key = config.ai_api_key.clone().unwrap_or_default();
+    let model = config.ai_model.clone();
+    let timeout = Duration::from_secs(config.ai_timeout_secs);
+
+    let http = ResilientHttp::new(
+        RetryPolicy {
+            max_retries: config.ai_max_retries,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(10),
+        },
+        CircuitBreaker::new(
+            config.ai_breaker_threshold,
+            Duration::from_secs(config.ai_breaker_cooldown_secs),
+        ),
+    );
+
+    match config.ai_provider {
+        ProviderKind::Gemini => Arc::new(GeminiProvider::new(http, api_key, model, config.ai_base_url.clone(), timeout)),
+        ProviderKind::OpenAi => Arc::new(OpenAiProvider::new(http, api_key, model, config.ai_base_url.clone(), timeout)),
+        ProviderKind::Ollama => Arc::new(OllamaProvider::new(http, model, config.ai_base_url.clone(), timeout)),
+    }
+}
@@ -0,0 +1,169 @@
+use anyhow::{Context, Result};
+use serde_json::{json, Value};
+use serenity::async_trait;
+use std::time::Duration;
+use tracing::{debug, error, info};
+
+use super::{ChatRequest, ChatResponse, FinishReason, LlmProvider, Part, ToolCall};
+use crate::ai::history::Role;
+use crate::ai::http::{AiError, ResilientHttp};
+use crate::usage::Usage;
+
+const DEFAULT_BASE_URL: &str = "http://localhost:11434";
+
+/// Provider for a local Ollama server, mainly used during development.
+pub struct OllamaProvider {
+    http: ResilientHttp,
+    model: String,
+    base_url: String,
+    timeout: Duration,
+}
+
+impl OllamaProvider {
+    pub fn new(http: ResilientHttp, model: String, base_url: Option<String>, timeout: Duration) -> Self {
+        info!("Initializing Ollama provider with model {}", model);
+        Self {
+            http,
+            model,
+            base_url: base_url
+                .unwrap_or_else(|| DEFAULT_BASE_URL.to_string())
+                .trim_end_matches('/')
+                .to_string(),
+            timeout,
+        }
+    }
+
+    fn build_messages(request: &ChatRequest) -> Vec<Value> {
+        let mut messages = vec![json!({
+            "role": "system",
+            "content": request.system
+        })];
+
+        for message in &request.messages {
+            let mut text = String::new();
+            let mut images = Vec::new();
+            let mut tool_calls = Vec::new();
+            for part in &message.parts {
+                match part {
+                    Part::Text(chunk) => text.push_str(chunk),
+                    Part::InlineData { data, .. } => images.push(Part::base64_data(data)),
+                    Part::ToolCall(call) => tool_calls.push(json!({
+                        "function": { "name": call.name, "arguments": call.args }
+                    })),
+                    Part::ToolResult(result) => messages.push(json!({
+                        "role": "tool",
+                        "tool_name": result.name,
+                        "content": result.content.to_string()
+                    })),
+                }
+            }
+
+            if text.is_empty() && images.is_empty() && tool_calls.is_empty() {
+                continue;
+            }
+
+            let mut entry = json!({
+                "role": match message.role {
+                    Role::User => "user",
+                    Role::Model => "assistant",
+                },
+                "content": text
+            });
+            if !images.is_empty() {
+                entry["images"] = json!(images);
+            }
+            if !tool_calls.is_empty() {
+                entry["tool_calls"] = json!(tool_calls);
+            }
+            messages.push(entry);
+        }
+
+        messages
+    }
+
+    fn build_payload(&self, request: &ChatRequest) -> Value {
+        let mut payload = json!({
+            "model": self.model,
+            "messages": Self::build_messages(request),
+            "stream": false,
+            "options": {
+                "temperature": request.temperature,
+                "num_predict": request.max_output_tokens,
+            }
+        });
+
+        if request.json_output {
+            payload["format"] = json!("json");
+        }
+
+        if !request.tools.is_empty() {
+            payload["tools"] = request.tools.iter()
+                .map(|tool| json!({
+                    "type": "function",
+                    "function": {
+                        "name": tool.name,
+                        "description": tool.description,
+                        "parameters": tool.parameters
+                    }
+                }))
+                .collect();
+        }
+
+        payload
+    }
+}
+
+#[async_trait]
+impl LlmProvider for OllamaProvider {
+    fn name(&self) -> &'static str {
+        "ollama"
+    }
+
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    async fn generate(&self, request: &ChatRequest) -> Result<ChatResponse> {
+        let url = format!("{}/api/chat", self.base_url);
+
+        debug!("Sending chat request to Ollama at {}", url);
+
+        let payload = self.build_payload(request);
+        let response = self.http
+            .send(|client| client.post(&url).json(&payload).timeout(self.timeout))
+            .await
+            .inspect_err(|e| error!("Ollama request failed: {}", e))?;
+
+        let json: Value = response.json().await
+            .map_err(|e| AiError::InvalidResponse(e.to_string()))
+            .context("Failed to parse Ollama response")?;
+
+        let text = json["message"]["content"].as_str().unwrap_or_default().to_string();
+        let tool_calls: Vec<ToolCall> = json["message"]["tool_calls"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .map(|call| ToolCall {
+                id: None,
+                name: call["function"]["name"].as_str().unwrap_or_default().to_string(),
+                args: call["function"]["arguments"].clone(),
+            })
+            .collect();
+
+        let finish_reason = match json["done_reason"].as_str() {
+            Some("length") => FinishReason::MaxTokens,
+            _ => FinishReason::Stop,
+        };
+
+        if text.is_empty() && tool_calls.is_empty() && finish_reason != FinishReason::MaxTokens {
+            return Err(AiError::EmptyResponse.into());
+        }
+
+        let usage = Usage {
+            prompt_tokens: json["prompt_eval_count"].as_u64().unwrap_or(0),
+            output_tokens: json["eval_count"].as_u64().unwrap_or(0),
+        };
+
+        Ok(ChatResponse { text, tool_calls, usage, finish_reason })
+    }
+}
@@ -0,0 +1,275 @@
+use anyhow::{Context, Result};
+use serde_json::{json, Value};
+use serenity::async_trait;
+use std::time::Duration;
+use tokio::sync::mpsc::UnboundedSender;
+use tracing::{debug, error, info};
+
+use super::sse::SseBuffer;
+use super::{ChatRequest, ChatResponse, FinishReason, LlmProvider, Part, ToolCall};
+use crate::ai::history::Role;
+use crate::ai::http::{AiError, BlockReason, ResilientHttp};
+use crate::usage::Usage;
+
+const DEFAULT_BASE_URL: &str = "https://api.openai.com/v1";
+
+/// Provider for any server exposing the OpenAI chat-completions API
+/// (OpenAI itself, OpenRouter, vLLM, LM Studio, ...).
+pub struct OpenAiProvider {
+    http: ResilientHttp,
+    api_key: String,
+    model: String,
+    base_url: String,
+    timeout: Duration,
+}
+
+impl OpenAiProvider {
+    pub fn new(http: ResilientHttp, api_key: String, model: String, base_url: Option<String>, timeout: Duration) -> Self {
+        info!("Initializing OpenAI-compatible provider with model {}", model);
+        Self {
+            http,
+            api_key,
+            model,
+            base_url: base_url
+                .unwrap_or_else(|| DEFAULT_BASE_URL.to_string())
+                .trim_end_matches('/')
+                .to_string(),
+            timeout,
+        }
+    }
+
+    async fn send(&self, payload: Value, timeout: Duration) -> Result<reqwest::Response, AiError> {
+        let url = format!("{}/chat/completions", self.base_url);
+
+        self.http
+            .send(|client| {
+                let builder = client.post(&url).json(&payload).timeout(timeout);
+                if self.api_key.is_empty() {
+                    builder
+                } else {
+                    builder.bearer_auth(&self.api_key)
+                }
+            })
+            .await
+            .inspect_err(|e| error!("Chat completions request failed: {}", e))
+    }
+
+    fn parse_usage(json: &Value) -> Option<Usage> {
+        let usage = json.get("usage").filter(|usage| usage.is_object())?;
+        Some(Usage {
+            prompt_tokens: usage["prompt_tokens"].as_u64().unwrap_or(0),
+            output_tokens: usage["completion_tokens"].as_u64().unwrap_or(0),
+        })
+    }
+
+    fn build_messages(request: &ChatRequest) -> Vec<Value> {
+        let mut messages = vec![json!({
+            "role": "system",
+            "content": request.system
+        })];
+
+        for message in &request.messages {
+            let mut text = String::new();
+            let mut images = Vec::new();
+            let mut tool_calls = Vec::new();
+            for part in &message.parts {
+                match part {
+                    Part::Text(chunk) => text.push_str(chunk),
+                    Part::InlineData { mime_type, data } => images.push(json!({
+                        "type": "image_url",
+                        "image_url": { "url": format!("data:{};base64,{}", mime_type, Part::base64_data(data)) }
+                    })),
+                    Part::ToolCall(call) => tool_calls.push(json!({
+                        "id": call.id.clone().unwrap_or_else(|| call.name.clone()),
+                        "type": "function",
+                        "function": { "name": call.name, "arguments": call.args.to_string() }
+                    })),
+                    Part::ToolResult(result) => messages.push(json!({
+                        "role": "tool",
+                        "tool_call_id": result.call_id.clone().unwrap_or_else(|| result.name.clone()),
+                        "content": result.content.to_string()
+                    })),
+                }
+            }
+
+            if text.is_empty() && images.is_empty() && tool_calls.is_empty() {
+                continue;
+            }
+
+            // Images require the multi-part content form
+            let content = if images.is_empty() {
+                json!(text)
+            } else {
+                let mut content = vec![json!({ "type": "text", "text": text })];
+                content.extend(images);
+                json!(content)
+            };
+
+            let mut entry = json!({
+                "role": match message.role {
+                    Role::User => "user",
+                    Role::Model => "assistant",
+                },
+                "content": content
+            });
+            if !tool_calls.is_empty() {
+                entry["tool_calls"] = json!(tool_calls);
+            }
+            messages.push(entry);
+        }
+
+        messages
+    }
+
+    fn build_payload(&self, request: &ChatRequest) -> Value {
+        let mut payload = json!({
+            "model": self.model,
+            "messages": Self::build_messages(request),
+            "temperature": request.temperature,
+            "max_tokens": request.max_output_tokens,
+        });
+
+        if request.json_output {
+            payload["response_format"] = json!({ "type": "json_object" });
+        }
+
+        if !request.tools.is_empty() {
+            payload["tools"] = request.tools.iter()
+                .map(|tool| json!({
+                    "type": "function",
+                    "function": {
+                        "name": tool.name,
+                        "description": tool.description,
+                        "parameters": tool.parameters
+                    }
+                }))
+                .collect();
+        }
+
+        payload
+    }
+
+    /// Maps a choice's `finish_reason`, treating content filtering as a withheld answer.
+    fn finish_reason(choice: &Value) -> Result<Option<FinishReason>, AiError> {
+        match choice["finish_reason"].as_str() {
+            None => Ok(None),
+            Some("length") => Ok(Some(FinishReason::MaxTokens)),
+            Some("content_filter") => Err(AiError::ResponseBlocked {
+                reason: BlockReason::Safety,
+                categories: Vec::new(),
+            }),
+            Some(_) => Ok(Some(FinishReason::Stop)),
+        }
+    }
+
+    fn parse_tool_calls(message: &Value) -> Vec<ToolCall> {
+        message["tool_calls"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .map(|call| ToolCall {
+                id: call["id"].as_str().map(str::to_string),
+                name: call["function"]["name"].as_str().unwrap_or_default().to_string(),
+                args: call["function"]["arguments"]
+                    .as_str()
+                    .and_then(|args| serde_json::from_str(args).ok())
+                    .unwrap_or_else(|| json!({})),
+            })
+            .collect()
+    }
+}
+
+#[async_trait]
+impl LlmProvider for OpenAiProvider {
+    fn name(&self) -> &'static str {
+        "openai"
+    }
+
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    async fn generate(&self, request: &ChatRequest) -> Result<ChatResponse> {
+        debug!("Sending chat completion request to {}", self.base_url);
+
+        let response = self.send(self.build_payload(request), self.timeout).await?;
+
+        let json: Value = response.json().await
+            .map_err(|e| AiError::InvalidResponse(e.to_string()))
+            .context("Failed to parse chat completions response")?;
+
+        let choice = json["choices"]
+            .get(0)
+            .ok_or_else(|| AiError::InvalidResponse("missing choices".to_string()))
+            .context("Invalid response structure from chat completions API")?;
+        let finish_reason = Self::finish_reason(choice)?.unwrap_or_default();
+
+        let message = &choice["message"];
+        let text = message["content"].as_str().unwrap_or_default().to_string();
+        let tool_calls = Self::parse_tool_calls(message);
+
+        if text.is_empty() && tool_calls.is_empty() && finish_reason != FinishReason::MaxTokens {
+            return Err(AiError::EmptyResponse.into());
+        }
+
+        let usage = Self::parse_usage(&json).unwrap_or_default();
+
+        Ok(ChatResponse { text, tool_calls, usage, finish_reason })
+    }
+
+    async fn generate_stream(&self, request: &ChatRequest, chunks: UnboundedSender<String>) -> Result<ChatResponse> {
+        if !request.tools.is_empty() {
+            // Streamed tool calls arrive as argument fragments; answer in one piece instead
+            let response = self.generate(request).await?;
+            if !response.text.is_empty() {
+                let _ = chunks.send(response.text.clone());
+            }
+            return Ok(response);
+        }
+
+        debug!("Opening chat completion stream at {}", self.base_url);
+
+        let mut payload = self.build_payload(request);
+        payload["stream"] = json!(true);
+        payload["stream_options"] = json!({ "include_usage": true });
+
+        // The timeout covers the whole stream, so allow more time than a single request
+        let mut response = self.send(payload, self.timeout * 4).await?;
+
+        let mut events = SseBuffer::default();
+        let mut text = String::new();
+        let mut usage = Usage::default();
+        let mut finish_reason = FinishReason::default();
+
+        while let Some(bytes) = ResilientHttp::next_chunk(&mut response).await.context("Chat completions stream interrupted")? {
+            for event in events.push(&bytes) {
+                if event.trim() == "[DONE]" {
+                    continue;
+                }
+                let json: Value = serde_json::from_str(&event)
+                    .map_err(|e| AiError::InvalidResponse(e.to_string()))
+                    .context("Failed to parse chat completions stream event")?;
+                // Usage arrives on a final chunk with an empty choices list
+                if let Some(event_usage) = Self::parse_usage(&json) {
+                    usage = event_usage;
+                }
+                let Some(choice) = json["choices"].get(0) else {
+                    continue;
+                };
+                if let Some(reason) = Self::finish_reason(choice)? {
+                    finish_reason = reason;
+                }
+                if let Some(chunk) = choice["delta"]["content"].as_str() {
+                    text.push_str(chunk);
+                    let _ = chunks.send(chunk.to_string());
+                }
+            }
+        }
+
+        if text.is_empty() && finish_reason != FinishReason::MaxTokens {
+            return Err(AiError::EmptyResponse.into());
+        }
+
+        Ok(ChatResponse { text, tool_calls: Vec::new(), usage, finish_reason })
+    }
+}
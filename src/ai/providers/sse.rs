@@ -0,0 +1,31 @@
+/// Incremental parser for `text/event-stream` bodies.
+///
+/// Bytes are fed in as they arrive from the network; complete `data:` payloads
+/// are returned once their terminating blank line has been received. Bytes are
+/// buffered raw so multi-byte characters split across network chunks survive.
+#[derive(Default)]
+pub struct SseBuffer {
+    buffer: Vec<u8>,
+}
+
+impl SseBuffer {
+    pub fn push(&mut self, bytes: &[u8]) -> Vec<String> {
+        // JSON payloads escape carriage returns, so any raw \r is a line ending
+        self.buffer.extend(bytes.iter().filter(|&&b| b != b'\r'));
+
+        let mut events = Vec::new();
+        while let Some(end) = self.buffer.windows(2).position(|w| w == b"\n\n") {
+            let event: Vec<u8> = self.buffer.drain(..end + 2).collect();
+            let event = String::from_utf8_lossy(&event);
+            let data: Vec<&str> = event
+                .lines()
+                .filter_map(|line| line.strip_prefix("data:"))
+                .map(|data| data.strip_prefix(' ').unwrap_or(data))
+                .collect();
+            if !data.is_empty() {
+                events.push(data.join("\n"));
+            }
+        }
+        events
+    }
+}
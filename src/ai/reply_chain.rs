@@ -0,0 +1,45 @@
+use serenity::model::channel::Message;
+use serenity::model::id::UserId;
+
+use super::providers::{ChatMessage, Part};
+
+/// Longest excerpt of a single message from the reply chain, in characters.
+const MAX_MESSAGE_CHARS: usize = 1500;
+
+fn excerpt(message: &Message) -> String {
+    let mut content: String = message.content.chars().take(MAX_MESSAGE_CHARS).collect();
+    if content.len() < message.content.len() {
+        content.push_str(" …");
+    }
+    for attachment in &message.attachments {
+        content.push_str(&format!("\n[attachment: {}]", attachment.filename));
+    }
+    content
+}
+
+/// Renders the messages a user replied to, oldest first, with their authors.
+/// Messages from `bot_id` are attributed to the model itself.
+pub fn context(chain: &[Message], bot_id: UserId) -> Option<String> {
+    if chain.is_empty() {
+        return None;
+    }
+
+    let mut context = String::from("[The user is replying to this earlier discussion, oldest message first]\n");
+    for message in chain {
+        let author = if message.author.id == bot_id {
+            "You (earlier answer)".to_string()
+        } else {
+            message.author.global_name.clone().unwrap_or_else(|| message.author.name.clone())
+        };
+        context.push_str(&format!("--- {}:\n{}\n", author, excerpt(message)));
+    }
+    context.push_str("[End of earlier discussion. The user's message follows]\n\n");
+    Some(context)
+}
+
+/// Puts the reply chain ahead of the user's own message.
+pub fn prepend(input: &mut ChatMessage, chain: &[Message], bot_id: UserId) {
+    if let Some(context) = context(chain, bot_id) {
+        input.parts.insert(0, Part::Text(context));
+    }
+}
@@ -0,0 +1,147 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HarmCategory {
+    Harassment,
+    HateSpeech,
+    SexuallyExplicit,
+    DangerousContent,
+}
+
+impl HarmCategory {
+    pub const ALL: [HarmCategory; 4] = [
+        HarmCategory::Harassment,
+        HarmCategory::HateSpeech,
+        HarmCategory::SexuallyExplicit,
+        HarmCategory::DangerousContent,
+    ];
+
+    pub fn api_name(&self) -> &'static str {
+        match self {
+            HarmCategory::Harassment => "HARM_CATEGORY_HARASSMENT",
+            HarmCategory::HateSpeech => "HARM_CATEGORY_HATE_SPEECH",
+            HarmCategory::SexuallyExplicit => "HARM_CATEGORY_SEXUALLY_EXPLICIT",
+            HarmCategory::DangerousContent => "HARM_CATEGORY_DANGEROUS_CONTENT",
+        }
+    }
+
+    /// Short identifier used in slash command choices.
+    pub fn key(&self) -> &'static str {
+        match self {
+            HarmCategory::Harassment => "harassment",
+            HarmCategory::HateSpeech => "hate_speech",
+            HarmCategory::SexuallyExplicit => "sexually_explicit",
+            HarmCategory::DangerousContent => "dangerous_content",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            HarmCategory::Harassment => "Harassment",
+            HarmCategory::HateSpeech => "Hate speech",
+            HarmCategory::SexuallyExplicit => "Sexually explicit",
+            HarmCategory::DangerousContent => "Dangerous content",
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|category| category.key() == key)
+    }
+
+    /// Human-readable label for a category name as reported by the API.
+    pub fn label_for_api_name(name: &str) -> String {
+        Self::ALL.into_iter()
+            .find(|category| category.api_name() == name)
+            .map(|category| category.label().to_string())
+            .unwrap_or_else(|| name.trim_start_matches("HARM_CATEGORY_").replace('_', " ").to_lowercase())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SafetyThreshold {
+    None,
+    OnlyHigh,
+    MediumAndAbove,
+    LowAndAbove,
+}
+
+impl SafetyThreshold {
+    pub const ALL: [SafetyThreshold; 4] = [
+        SafetyThreshold::None,
+        SafetyThreshold::OnlyHigh,
+        SafetyThreshold::MediumAndAbove,
+        SafetyThreshold::LowAndAbove,
+    ];
+
+    pub fn api_name(&self) -> &'static str {
+        match self {
+            SafetyThreshold::None => "BLOCK_NONE",
+            SafetyThreshold::OnlyHigh => "BLOCK_ONLY_HIGH",
+            SafetyThreshold::MediumAndAbove => "BLOCK_MEDIUM_AND_ABOVE",
+            SafetyThreshold::LowAndAbove => "BLOCK_LOW_AND_ABOVE",
+        }
+    }
+
+    pub fn key(&self) -> &'static str {
+        match self {
+            SafetyThreshold::None => "block_none",
+            SafetyThreshold::OnlyHigh => "block_only_high",
+            SafetyThreshold::MediumAndAbove => "block_medium_and_above",
+            SafetyThreshold::LowAndAbove => "block_low_and_above",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            SafetyThreshold::None => "Block none",
+            SafetyThreshold::OnlyHigh => "Block only high",
+            SafetyThreshold::MediumAndAbove => "Block medium and above",
+            SafetyThreshold::LowAndAbove => "Block low and above",
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|threshold| threshold.key() == key)
+    }
+}
+
+/// Blocking threshold for each of the four configurable harm categories.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SafetySettings {
+    pub harassment: SafetyThreshold,
+    pub hate_speech: SafetyThreshold,
+    pub sexually_explicit: SafetyThreshold,
+    pub dangerous_content: SafetyThreshold,
+}
+
+impl Default for SafetySettings {
+    fn default() -> Self {
+        Self {
+            harassment: SafetyThreshold::MediumAndAbove,
+            hate_speech: SafetyThreshold::MediumAndAbove,
+            sexually_explicit: SafetyThreshold::MediumAndAbove,
+            dangerous_content: SafetyThreshold::MediumAndAbove,
+        }
+    }
+}
+
+impl SafetySettings {
+    pub fn get(&self, category: HarmCategory) -> SafetyThreshold {
+        match category {
+            HarmCategory::Harassment => self.harassment,
+            HarmCategory::HateSpeech => self.hate_speech,
+            HarmCategory::SexuallyExplicit => self.sexually_explicit,
+            HarmCategory::DangerousContent => self.dangerous_content,
+        }
+    }
+
+    pub fn set(&mut self, category: HarmCategory, threshold: SafetyThreshold) {
+        match category {
+            HarmCategory::Harassment => self.harassment = threshold,
+            HarmCategory::HateSpeech => self.hate_speech = threshold,
+            HarmCategory::SexuallyExplicit => self.sexually_explicit = threshold,
+            HarmCategory::DangerousContent => self.dangerous_content = threshold,
+        }
+    }
+}
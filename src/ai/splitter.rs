@@ -0,0 +1,134 @@
+/// Discord's hard limit on the length of a message's content.
+pub const DISCORD_MESSAGE_LIMIT: usize = 2000;
+
+const TRUNCATION_NOTE: &str = "\n*(Answer shortened. Ask for the rest if you need it.)*";
+
+/// Splits a model answer into Discord-sized messages.
+///
+/// Splits prefer paragraph breaks, then line breaks, and only cut inside a line
+/// of prose when it is longer than the limit. Code is only split between lines,
+/// since a cut inside a line would change it; code with longer lines is moved to
+/// a file by `postprocess::extract_code_files` beforehand. When a split falls
+/// inside a fenced code block the fence is closed and reopened with the same
+/// language tag in the next message. At most `max_messages` messages are returned; anything beyond
+/// that is dropped and the last message says so.
+pub fn split_response(text: &str, max_len: usize, max_messages: usize) -> Vec<String> {
+    let max_messages = max_messages.max(1);
+    let mut chunks = split_all(text.trim(), max_len);
+
+    if chunks.len() > max_messages {
+        chunks.truncate(max_messages);
+        let last = chunks.pop().unwrap_or_default();
+        let room = max_len.saturating_sub(TRUNCATION_NOTE.chars().count());
+        let mut shortened = split_all(&last, room).into_iter().next().unwrap_or_default();
+        shortened.push_str(TRUNCATION_NOTE);
+        chunks.push(shortened);
+    }
+
+    chunks
+}
+
+fn split_all(text: &str, max_len: usize) -> Vec<String> {
+    let mut chunks = Vec::new();
+    let mut current = String::new();
+    // Language tag of the currently open code fence, if any
+    let mut fence: Option<String> = None;
+    // Byte offset in `current` just after the last blank line outside a code block
+    let mut last_break: Option<usize> = None;
+
+    // Leave room for the fence we may have to reopen at the start of a message
+    let line_limit = max_len.saturating_sub(24).max(1);
+
+    // Only prose is wrapped; lines inside code fences are kept whole
+    let mut in_code: Option<String> = None;
+    let lines: Vec<String> = text.split_inclusive('\n')
+        .flat_map(|line| {
+            let wrapped = if in_code.is_some() { vec![line.to_string()] } else { hard_wrap(line, line_limit) };
+            in_code = fence_after(&in_code, line);
+            wrapped
+        })
+        .collect();
+
+    for line in lines {
+        let next_fence = fence_after(&fence, &line);
+        let closing = if next_fence.is_some() { 4 } else { 0 };
+
+        if char_len(&current) + char_len(&line) + closing > max_len {
+            // Prefer a paragraph break in the second half of the message, keeping
+            // it for later lines when it is too early to use yet
+            if let Some(at) = last_break.filter(|&at| at > current.len() / 2) {
+                let tail = current.split_off(at);
+                push_chunk(&mut chunks, std::mem::replace(&mut current, tail));
+                last_break = None;
+            }
+
+            if char_len(&current) + char_len(&line) + closing > max_len && !current.trim().is_empty() {
+                if fence.is_some() {
+                    if !current.ends_with('\n') {
+                        current.push('\n');
+                    }
+                    current.push_str("```");
+                }
+                let reopened = fence.as_ref().map(|lang| format!("```{}\n", lang)).unwrap_or_default();
+                push_chunk(&mut chunks, std::mem::replace(&mut current, reopened));
+                last_break = None;
+            }
+        }
+
+        current.push_str(&line);
+        fence = next_fence;
+
+        if fence.is_none() && line.trim().is_empty() {
+            last_break = Some(current.len());
+        }
+    }
+
+    push_chunk(&mut chunks, current);
+    chunks
+}
+
+fn push_chunk(chunks: &mut Vec<String>, chunk: String) {
+    let chunk = chunk.trim_end();
+    if !chunk.trim().is_empty() {
+        chunks.push(chunk.to_string());
+    }
+}
+
+/// Returns the fence state after `line`: the language tag of an open block, or `None`.
+fn fence_after(fence: &Option<String>, line: &str) -> Option<String> {
+    let trimmed = line.trim();
+    match (fence, trimmed.strip_prefix("```")) {
+        (None, Some(lang)) => Some(lang.trim().to_string()),
+        (Some(_), Some(_)) => None,
+        (current, None) => current.clone(),
+    }
+}
+
+/// Cuts a line longer than `limit` characters, preferring whitespace.
+fn hard_wrap(line: &str, limit: usize) -> Vec<String> {
+    if char_len(line) <= limit {
+        return vec![line.to_string()];
+    }
+
+    let mut pieces = Vec::new();
+    let mut rest = line;
+    while char_len(rest) > limit {
+        let boundary = rest.char_indices().nth(limit).map_or(rest.len(), |(i, _)| i);
+        let cut = rest[..boundary]
+            .char_indices()
+            .rev()
+            .find(|(_, c)| c.is_whitespace())
+            .filter(|&(i, _)| i > boundary / 2)
+            .map_or(boundary, |(i, c)| i + c.len_utf8());
+        pieces.push(format!("{}\n", rest[..cut].trim_end()));
+        rest = &rest[cut..];
+    }
+    if !rest.is_empty() {
+        pieces.push(rest.to_string());
+    }
+    pieces
+}
+
+fn char_len(text: &str) -> usize {
+    text.chars().count()
+}
@@ -0,0 +1,119 @@
+use anyhow::{Context, Result};
+use serde_json::Value;
+use serenity::model::channel::Message;
+use serenity::model::id::MessageId;
+
+use super::http::AiError;
+
+/// Longest excerpt of a single message included in the transcript, in characters.
+const MAX_MESSAGE_CHARS: usize = 600;
+
+/// Character budget for the whole transcript; the oldest messages are dropped first.
+const MAX_TRANSCRIPT_CHARS: usize = 24_000;
+
+pub const SYSTEM_PROMPT: &str = "You summarize Discord help channel discussions about Roblox development \
+for people who just joined. You are given a transcript where every line starts with a message ID in brackets. \
+Respond with a single JSON object and nothing else, using this shape:\n\
+{\"key_points\": [\"...\"], \"code_solutions\": [{\"description\": \"...\", \"message_id\": \"...\"}], \
+\"relevant_messages\": [{\"description\": \"...\", \"message_id\": \"...\"}]}\n\
+- key_points: at most 8 short sentences covering the questions asked, what was tried and what was concluded\n\
+- code_solutions: code or fixes that solved a problem, described in one sentence, pointing at the message that contains them\n\
+- relevant_messages: at most 5 other messages worth reading in full\n\
+Only use message IDs that appear in the transcript. Use empty lists when there is nothing to report.";
+
+/// A summary item pointing at a message in the channel.
+#[derive(Debug, Clone)]
+pub struct Reference {
+    pub description: String,
+    pub message_id: Option<MessageId>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Summary {
+    pub key_points: Vec<String>,
+    pub code_solutions: Vec<Reference>,
+    pub relevant_messages: Vec<Reference>,
+}
+
+/// Renders messages, given newest first as Discord returns them, as chronological
+/// `[id] author: content` lines. The oldest messages are dropped once the budget
+/// is used up. Returns the transcript and how many messages it holds.
+pub fn transcript(messages: &[Message]) -> (String, usize) {
+    let mut lines: Vec<String> = Vec::new();
+    let mut total = 0;
+
+    for message in messages {
+        let mut content: String = message.content.chars().take(MAX_MESSAGE_CHARS).collect();
+        if content.len() < message.content.len() {
+            content.push_str(" …");
+        }
+        for attachment in &message.attachments {
+            content.push_str(&format!(" [attachment: {}]", attachment.filename));
+        }
+        if content.trim().is_empty() {
+            continue;
+        }
+
+        let line = format!(
+            "[{}] {}{}: {}",
+            message.id,
+            message.author.global_name.as_ref().unwrap_or(&message.author.name),
+            if message.author.bot { " (bot)" } else { "" },
+            content.replace('\n', " ")
+        );
+        total += line.len() + 1;
+        if total > MAX_TRANSCRIPT_CHARS {
+            break;
+        }
+        lines.push(line);
+    }
+
+    lines.reverse();
+    let count = lines.len();
+    (lines.join("\n"), count)
+}
+
+fn references(value: &Value, known: &[MessageId]) -> Vec<Reference> {
+    value.as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|item| {
+            let description = item["description"].as_str()?.trim().to_string();
+            let message_id = match &item["message_id"] {
+                Value::String(id) => id.trim().trim_matches(['[', ']']).parse::<u64>().ok(),
+                Value::Number(id) => id.as_u64(),
+                _ => None,
+            }
+            .map(MessageId::new)
+            .filter(|id| known.contains(id));
+            (!description.is_empty()).then_some(Reference { description, message_id })
+        })
+        .collect()
+}
+
+/// Parses the model's JSON answer, dropping references to messages that were
+/// not in the transcript.
+pub fn parse(response: &str, known: &[MessageId]) -> Result<Summary> {
+    let trimmed = response.trim();
+    let json = trimmed
+        .strip_prefix("```json")
+        .or_else(|| trimmed.strip_prefix("```"))
+        .and_then(|rest| rest.strip_suffix("```"))
+        .unwrap_or(trimmed);
+
+    let value: Value = serde_json::from_str(json.trim())
+        .map_err(|e| AiError::InvalidResponse(e.to_string()))
+        .context("Summary was not valid JSON")?;
+
+    Ok(Summary {
+        key_points: value["key_points"].as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(Value::as_str)
+            .map(|point| point.trim().to_string())
+            .filter(|point| !point.is_empty())
+            .collect(),
+        code_solutions: references(&value["code_solutions"], known),
+        relevant_messages: references(&value["relevant_messages"], known),
+    })
+}
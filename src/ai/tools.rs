@@ -0,0 +1,164 @@
+use serde_json::{json, Value};
+use serenity::model::id::{GuildId, UserId};
+use serenity::model::prelude::User;
+use serenity::prelude::Context;
+use tracing::{debug, info};
+
+use super::providers::{ToolCall, ToolDeclaration};
+use crate::commands;
+
+/// Functions the model can call to look up live Discord data instead of guessing.
+pub fn declarations() -> Vec<ToolDeclaration> {
+    vec![
+        ToolDeclaration {
+            name: "get_server_info",
+            description: "Get details about the Discord server the conversation is in: name, owner, member count, creation date, role and channel counts, boost level and verification level.",
+            parameters: json!({ "type": "object", "properties": {} }),
+        },
+        ToolDeclaration {
+            name: "get_member_count",
+            description: "Get the current number of members in the Discord server the conversation is in.",
+            parameters: json!({ "type": "object", "properties": {} }),
+        },
+        ToolDeclaration {
+            name: "get_user_profile",
+            description: "Look up a Discord user's profile: username, display name, nickname, avatar, account creation date, server join date and roles.",
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "user": {
+                        "type": "string",
+                        "description": "A user ID, mention or username. Omit to look up the user who is asking."
+                    }
+                }
+            }),
+        },
+        ToolDeclaration {
+            name: "get_bot_latency",
+            description: "Get the bot's current Discord gateway latency in milliseconds.",
+            parameters: json!({ "type": "object", "properties": {} }),
+        },
+    ]
+}
+
+/// Runs a function requested by the model and returns its result as JSON.
+///
+/// Failures are reported to the model as an `error` field rather than aborting
+/// the conversation, so it can explain the problem to the user.
+pub async fn execute(ctx: &Context, call: &ToolCall, user: &User, guild_id: Option<GuildId>) -> Value {
+    info!("Running tool {} for user {}", call.name, user.tag());
+    debug!("Tool arguments: {}", call.args);
+
+    match call.name.as_str() {
+        "get_server_info" => server_info(ctx, guild_id).await,
+        "get_member_count" => member_count(ctx, guild_id),
+        "get_user_profile" => user_profile(ctx, call.args["user"].as_str(), user, guild_id).await,
+        "get_bot_latency" => bot_latency(ctx).await,
+        unknown => json!({ "error": format!("Unknown function '{}'", unknown) }),
+    }
+}
+
+fn not_in_guild() -> Value {
+    json!({ "error": "This conversation is not in a server." })
+}
+
+async fn server_info(ctx: &Context, guild_id: Option<GuildId>) -> Value {
+    let Some(guild_id) = guild_id else {
+        return not_in_guild();
+    };
+    let Some(info) = commands::server_info(ctx, guild_id) else {
+        return json!({ "error": "Server information is not available right now." });
+    };
+
+    let owner = info.owner_id.to_user(&ctx.http).await.map_or("Unknown".to_string(), |u| u.tag());
+
+    json!({
+        "name": info.name,
+        "id": info.id.to_string(),
+        "owner": owner,
+        "owner_id": info.owner_id.to_string(),
+        "member_count": info.member_count,
+        "created": info.created_at,
+        "roles": info.role_count,
+        "channels": info.channel_count,
+        "boost_level": info.premium_tier.replace("Tier", "Level"),
+        "boosters": info.boosters,
+        "verification_level": info.verification_level,
+    })
+}
+
+fn member_count(ctx: &Context, guild_id: Option<GuildId>) -> Value {
+    let Some(guild_id) = guild_id else {
+        return not_in_guild();
+    };
+    match commands::member_count(ctx, guild_id) {
+        Some((name, count)) => json!({ "server": name, "member_count": count }),
+        None => json!({ "error": "Member count is not available right now." }),
+    }
+}
+
+async fn resolve_user(ctx: &Context, query: Option<&str>, asker: &User, guild_id: Option<GuildId>) -> Option<UserId> {
+    let query = query.map(str::trim).filter(|q| !q.is_empty())?;
+
+    let digits = query.trim_start_matches("<@").trim_start_matches('!').trim_end_matches('>');
+    if let Ok(id) = digits.parse::<u64>() {
+        return Some(UserId::new(id));
+    }
+
+    if query.eq_ignore_ascii_case(&asker.name) {
+        return Some(asker.id);
+    }
+
+    let members = guild_id?.search_members(&ctx.http, query.trim_start_matches('@'), Some(1)).await.ok()?;
+    members.first().map(|member| member.user.id)
+}
+
+async fn user_profile(ctx: &Context, query: Option<&str>, asker: &User, guild_id: Option<GuildId>) -> Value {
+    let user_id = match query {
+        Some(_) => match resolve_user(ctx, query, asker, guild_id).await {
+            Some(id) => id,
+            None => return json!({ "error": format!("No user matching '{}' was found.", query.unwrap_or_default()) }),
+        },
+        None => asker.id,
+    };
+
+    let user = match user_id.to_user(&ctx.http).await {
+        Ok(user) => user,
+        Err(e) => return json!({ "error": format!("Could not fetch user: {}", e) }),
+    };
+
+    let mut profile = json!({
+        "username": user.tag(),
+        "id": user.id.to_string(),
+        "display_name": user.global_name.as_ref().unwrap_or(&user.name),
+        "avatar": user.avatar_url(),
+        "bot": user.bot,
+        "account_created": user.id.created_at().date().to_string(),
+    });
+
+    if let Some(guild_id) = guild_id {
+        match guild_id.member(&ctx.http, user.id).await {
+            Ok(member) => {
+                profile["nickname"] = json!(member.nick);
+                profile["joined_server"] = json!(member.joined_at.map(|at| at.date().to_string()));
+                let roles: Vec<String> = ctx.cache.guild(guild_id)
+                    .map(|guild| member.roles.iter()
+                        .filter_map(|role_id| guild.roles.get(role_id).map(|role| role.name.clone()))
+                        .collect())
+                    .unwrap_or_default();
+                profile["roles"] = json!(roles);
+            }
+            Err(e) => debug!("Could not fetch member info: {}", e),
+        }
+    }
+
+    profile
+}
+
+async fn bot_latency(ctx: &Context) -> Value {
+    match commands::gateway_latency(ctx).await {
+        Ok(Some(latency)) => json!({ "gateway_latency_ms": latency.as_millis() as u64 }),
+        Ok(None) => json!({ "error": "Latency has not been measured yet. Try again in a minute." }),
+        Err(e) => json!({ "error": e.to_string() }),
+    }
+}
@@ -0,0 +1,306 @@
+/// Top-level services a script path can start with.
+const SERVICES: &[&str] = &[
+    "Workspace", "Players", "Lighting", "ReplicatedFirst", "ReplicatedStorage", "ServerScriptService",
+    "ServerStorage", "StarterGui", "StarterPack", "StarterPlayer", "SoundService", "Chat", "TextChatService",
+];
+
+/// A line in a script, such as `ServerScriptService.Main` line 42.
+#[derive(Debug, Clone)]
+pub struct Location {
+    pub script: String,
+    pub line: u32,
+}
+
+impl Location {
+    /// The script's own name, without the services and folders it sits in.
+    pub fn script_name(&self) -> &str {
+        self.script.rsplit('.').next().unwrap_or(&self.script)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct StackFrame {
+    pub location: Location,
+    pub function: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// `attempt to index nil with 'Humanoid'`
+    IndexNil(Option<String>),
+    CallNil,
+    Arithmetic,
+    Concatenate,
+    Compare,
+    /// `Head is not a valid member of Model "Workspace.Dummy"`
+    NotValidMember { member: String, parent: String },
+    /// `Infinite yield possible on 'Workspace:WaitForChild("Part")'`
+    InfiniteYield(String),
+    Timeout,
+    StackOverflow,
+    HttpDisabled,
+    MissingArgument,
+    Other,
+}
+
+impl ErrorKind {
+    fn classify(message: &str) -> Self {
+        let lower = message.to_lowercase();
+        if let Some(rest) = message.split_once("attempt to index nil").map(|(_, rest)| rest) {
+            let key = rest.trim().strip_prefix("with ").map(|key| key.trim().trim_matches(['\'', '"']).to_string());
+            ErrorKind::IndexNil(key)
+        } else if lower.contains("attempt to call a nil value") || lower.contains("attempt to call nil") {
+            ErrorKind::CallNil
+        } else if lower.contains("attempt to perform arithmetic") {
+            ErrorKind::Arithmetic
+        } else if lower.contains("attempt to concatenate") {
+            ErrorKind::Concatenate
+        } else if lower.contains("attempt to compare") {
+            ErrorKind::Compare
+        } else if let Some((member, parent)) = message.split_once(" is not a valid member of ") {
+            ErrorKind::NotValidMember { member: member.trim().to_string(), parent: parent.trim().to_string() }
+        } else if lower.contains("exhausted allowed execution time") {
+            ErrorKind::Timeout
+        } else if lower.contains("stack overflow") {
+            ErrorKind::StackOverflow
+        } else if lower.contains("http requests are not enabled") {
+            ErrorKind::HttpDisabled
+        } else if lower.starts_with("argument") && lower.contains("missing or nil") {
+            ErrorKind::MissingArgument
+        } else {
+            ErrorKind::Other
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ErrorKind::IndexNil(_) => "Indexing nil",
+            ErrorKind::CallNil => "Calling nil",
+            ErrorKind::Arithmetic => "Arithmetic on a non-number",
+            ErrorKind::Concatenate => "Concatenating a non-string",
+            ErrorKind::Compare => "Invalid comparison",
+            ErrorKind::NotValidMember { .. } => "Missing child or property",
+            ErrorKind::InfiniteYield(_) => "Infinite yield",
+            ErrorKind::Timeout => "Script timeout",
+            ErrorKind::StackOverflow => "Stack overflow",
+            ErrorKind::HttpDisabled => "HTTP requests disabled",
+            ErrorKind::MissingArgument => "Missing argument",
+            ErrorKind::Other => "Runtime error",
+        }
+    }
+}
+
+/// A Roblox error found in a message, with the stack trace that came with it.
+#[derive(Debug, Clone)]
+pub struct ErrorReport {
+    pub message: String,
+    pub kind: ErrorKind,
+    /// Where the error was raised, from the error line or else the top of the stack.
+    pub location: Option<Location>,
+    /// Innermost frame first, as Roblox prints them.
+    pub stack: Vec<StackFrame>,
+}
+
+/// Drops the timestamp the Output window puts in front of each line, e.g. `12:04:51.337  `.
+fn strip_timestamp(line: &str) -> &str {
+    match line.split_once(char::is_whitespace) {
+        Some((first, rest)) if first.contains(':') && first.chars().all(|c| c.is_ascii_digit() || c == ':' || c == '.') => {
+            rest.trim_start()
+        }
+        _ => line,
+    }
+}
+
+/// Drops the `  -  Server - Main:42` origin the Output window appends to each line.
+fn strip_origin(line: &str) -> &str {
+    ["  -  Server", "  -  Client", "  -  Studio", " - Server - ", " - Client - ", " - Studio - "].iter()
+        .filter_map(|origin| line.find(origin))
+        .min()
+        .map_or(line, |index| &line[..index])
+        .trim_end()
+}
+
+fn is_script_path(script: &str) -> bool {
+    let root = script.split('.').next().unwrap_or_default();
+    SERVICES.iter().any(|service| service.eq_ignore_ascii_case(root))
+}
+
+/// Splits `<script>:<line><rest>` at the first `:<digits>` whose remainder `rest_ok` accepts.
+fn split_location(line: &str, rest_ok: impl Fn(&str) -> bool) -> Option<(Location, &str)> {
+    line.match_indices(':').find_map(|(index, _)| {
+        let after = &line[index + 1..];
+        let digits = after.chars().take_while(char::is_ascii_digit).count();
+        let rest = &after[digits..];
+        let script = line[..index].trim();
+        if digits == 0 || script.is_empty() || !rest_ok(rest) {
+            return None;
+        }
+        let line = after[..digits].parse().ok()?;
+        Some((Location { script: script.to_string(), line }, rest))
+    })
+}
+
+/// `ServerScriptService.Main:42: attempt to index nil with 'Humanoid'`
+fn error_line(line: &str) -> Option<(Location, String)> {
+    let (location, rest) = split_location(line, |rest| rest.starts_with(": "))?;
+    Some((location, rest[2..].trim().to_string()))
+}
+
+/// `Script 'Workspace.Part.Script', Line 12 - function onTouched` or, in current
+/// Studio output, `Workspace.Part.Script:12 function onTouched`.
+fn stack_frame(line: &str) -> Option<StackFrame> {
+    let (location, rest) = match line.strip_prefix("Script '") {
+        Some(rest) => {
+            let (script, rest) = rest.split_once("', Line ")?;
+            let digits = rest.chars().take_while(char::is_ascii_digit).count();
+            let location = Location { script: script.to_string(), line: rest[..digits].parse().ok()? };
+            (location, rest[digits..].trim_start().trim_start_matches('-'))
+        }
+        None => split_location(line, |rest| rest.is_empty() || rest.starts_with(' '))?,
+    };
+    let function = rest.trim()
+        .strip_prefix("function ")
+        .map(|function| function.trim().to_string())
+        .filter(|function| !function.is_empty());
+    Some(StackFrame { location, function })
+}
+
+/// Finds a Roblox error, or an infinite yield warning, and its stack trace in a message.
+pub fn parse(content: &str) -> Option<ErrorReport> {
+    let mut error: Option<(Option<Location>, String)> = None;
+    let mut stack = Vec::new();
+    let mut in_stack = false;
+
+    for raw in content.lines() {
+        let line = strip_origin(strip_timestamp(raw.trim().trim_matches('`').trim()));
+        if line.is_empty() {
+            continue;
+        }
+        if line.contains("Stack Begin") {
+            in_stack = true;
+            continue;
+        }
+        if line.contains("Stack End") {
+            in_stack = false;
+            continue;
+        }
+        if in_stack || line.starts_with("Script '") {
+            if let Some(frame) = stack_frame(line) {
+                stack.push(frame);
+                continue;
+            }
+        }
+        if error.is_some() {
+            continue;
+        }
+
+        if let Some((location, message)) = error_line(line) {
+            if is_script_path(&location.script) || ErrorKind::classify(&message) != ErrorKind::Other {
+                error = Some((Some(location), message));
+            }
+        } else if let Some(index) = line.find("Infinite yield possible on ") {
+            error = Some((None, line[index..].to_string()));
+        }
+    }
+
+    let (location, message) = error?;
+    let kind = match message.strip_prefix("Infinite yield possible on ") {
+        Some(target) => ErrorKind::InfiniteYield(target.trim().trim_matches('\'').to_string()),
+        None => ErrorKind::classify(&message),
+    };
+    let location = location.or_else(|| stack.first().map(|frame| frame.location.clone()));
+    Some(ErrorReport { message, kind, location, stack })
+}
+
+/// Built-in explanation for the common errors, so they are answered without a model call.
+pub fn canned_explanation(report: &ErrorReport) -> Option<String> {
+    let at = report.location.as_ref()
+        .map(|location| format!("On line {} of `{}`", location.line, location.script_name()))
+        .unwrap_or_else(|| "In your script".to_string());
+
+    let text = match &report.kind {
+        ErrorKind::IndexNil(key) => {
+            let key = key.as_deref().map(|key| format!("`.{}`", key)).unwrap_or_else(|| "a field".to_string());
+            format!(
+                "{at}, the script reads {key} from something that is `nil`, so the value in front of {key} does not exist yet.\n\n\
+                **Common causes**\n\
+                - `player.Character` is `nil` until the character spawns: use `player.Character or player.CharacterAdded:Wait()`\n\
+                - `FindFirstChild` returned `nil` because the object is missing or named differently\n\
+                - A variable was never assigned, or a function returned nothing\n\n\
+                Check the value before indexing it (`if humanoid then ... end`) or wait for it with `WaitForChild`."
+            )
+        }
+        ErrorKind::CallNil => format!(
+            "{at}, the script calls something that is `nil`, usually a misspelled method or function name, \
+            a function defined *below* the line that calls it, or a ModuleScript that did not return the function.\n\n\
+            Check the spelling and capitalization (`:FindFirstChild`, not `:findFirstChild`), and make sure local \
+            functions are defined before they are used."
+        ),
+        ErrorKind::Arithmetic => format!(
+            "{at}, math (`+`, `-`, `*`, `/`) was done on a value that is not a number, often `nil` or a string.\n\n\
+            Make sure every operand has a value (leaderstats values need `.Value`), and convert text with `tonumber(...)`."
+        ),
+        ErrorKind::Concatenate => format!(
+            "{at}, `..` was used to join text with a value that is `nil` or not a string.\n\n\
+            Wrap the value in `tostring(...)`, or use string interpolation: `` `Coins: {{coins}}` ``."
+        ),
+        ErrorKind::Compare => format!(
+            "{at}, `<`, `>`, `<=` or `>=` compared values that cannot be ordered, such as a number with `nil` or a string.\n\n\
+            Check that both sides are numbers; `tonumber(...)` converts text like TextBox input."
+        ),
+        ErrorKind::NotValidMember { member, parent } => format!(
+            "{at}, `{member}` was looked up on {parent}, but it has no child or property with that name *at that moment*.\n\n\
+            - If the object is created or replicated later, use `:WaitForChild(\"{member}\")`\n\
+            - If it may not exist at all, use `:FindFirstChild(\"{member}\")` and check for `nil`\n\
+            - Otherwise check the spelling and capitalization in the Explorer"
+        ),
+        ErrorKind::InfiniteYield(target) => format!(
+            "`{target}` has been waiting for more than 5 seconds without the object appearing. \
+            This is a warning, but the script stays stuck on that line.\n\n\
+            Check that the object's name and parent match exactly, that it is not created only on the server while \
+            a LocalScript waits for it (or the other way around), and pass a timeout (`:WaitForChild(name, 10)`) \
+            if it is allowed to be missing."
+        ),
+        ErrorKind::Timeout => format!(
+            "{at}, a loop ran for too long without yielding, so Roblox stopped the script.\n\n\
+            Add `task.wait()` inside long-running `while` or `repeat` loops, and check that the loop's exit condition can become true."
+        ),
+        ErrorKind::StackOverflow => format!(
+            "{at}, a function kept calling itself (directly or through other functions) until the call stack ran out.\n\n\
+            Look for recursion without a stopping case, or a property `Changed` handler that sets the property it listens to."
+        ),
+        ErrorKind::HttpDisabled => "The game tried to use `HttpService`, which is off by default.\n\n\
+            Enable it in **Game Settings → Security → Allow HTTP Requests**, and publish the place for live servers.".to_string(),
+        ErrorKind::MissingArgument => format!(
+            "{at}, a Roblox function was called without one of its required arguments, or with a variable that is `nil`.\n\n\
+            Check the argument list against the API reference and make sure each variable passed in has a value."
+        ),
+        ErrorKind::Other => return None,
+    };
+    Some(text)
+}
+
+/// Request to the model for errors without a built-in explanation.
+pub fn prompt(report: &ErrorReport, original: &str) -> String {
+    let mut prompt = String::from(
+        "Explain this Roblox error to the developer who hit it: what it means, the most likely cause on the \
+        failing line, and how to fix it, with a short corrected Luau snippet if it helps. \
+        Keep the whole answer under 1200 characters.\n\n",
+    );
+    prompt.push_str(&format!("Error: {}\nType: {}\n", report.message, report.kind.label()));
+    if let Some(location) = &report.location {
+        prompt.push_str(&format!("Failing line: {} line {}\n", location.script, location.line));
+    }
+    if !report.stack.is_empty() {
+        prompt.push_str("Call stack, innermost first:\n");
+        for frame in &report.stack {
+            prompt.push_str(&format!("- {} line {}", frame.location.script, frame.location.line));
+            if let Some(function) = &frame.function {
+                prompt.push_str(&format!(" in {}", function));
+            }
+            prompt.push('\n');
+        }
+    }
+    prompt.push_str(&format!("\nTheir message:\n{}", original));
+    prompt
+}
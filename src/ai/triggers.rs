@@ -0,0 +1,313 @@
+use serenity::client::Context;
+use serenity::model::channel::Message;
+use serenity::model::id::GuildId;
+
+use super::traceback;
+use crate::guild_settings::TriggerMode;
+
+/// Roblox development keywords that make a question worth answering unprompted.
+const DEV_KEYWORDS: &[&str] = &[
+    "roblox", "luau", "script", "scripting", "studio", "rbx", "remote event",
+    "remote function", "remoteevent", "remotefunction", "datastore", "leaderstats", "gui", "screengui",
+    "local script", "localscript", "server script", "game development", "rbxasset", "rbxassetid",
+    "datastoreservice",
+];
+
+/// Phrases that ask for help.
+const HELP_PATTERNS: &[&str] = &[
+    "help me", "can you help", "i need help", "how do i", "how to",
+    "what is", "explain", "show me", "teach me", "can you",
+    "do you know", "question about",
+];
+
+/// The rules that can make the bot answer a message, in the order they are checked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rule {
+    Conversation,
+    DirectMessage,
+    UserMention,
+    RoleMention,
+    ReplyToBot,
+    Alias,
+    RobloxError,
+    HelpRequest,
+    DevQuestion,
+}
+
+impl Rule {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Rule::Conversation => "Ongoing conversation",
+            Rule::DirectMessage => "Direct message",
+            Rule::UserMention => "@mention of the bot",
+            Rule::RoleMention => "@mention of the bot's role",
+            Rule::ReplyToBot => "Reply to the bot",
+            Rule::Alias => "Name or alias",
+            Rule::RobloxError => "Pasted Roblox error",
+            Rule::HelpRequest => "Help request about Roblox",
+            Rule::DevQuestion => "Roblox question",
+        }
+    }
+
+    /// Whether the rule applies in `mode`. Passive rules read the message text,
+    /// which servers in mention-only mode opt out of.
+    pub fn applies_in(&self, mode: TriggerMode) -> bool {
+        let passive = matches!(self, Rule::Alias | Rule::RobloxError | Rule::HelpRequest | Rule::DevQuestion);
+        !passive || mode == TriggerMode::KeywordPassive
+    }
+}
+
+/// Outcome of one rule for a message: what matched, or why nothing did.
+#[derive(Debug, Clone)]
+pub struct Check {
+    pub rule: Rule,
+    pub matched: bool,
+    pub enabled: bool,
+    pub detail: String,
+}
+
+/// Every rule's outcome for a message, and the first enabled rule that matched.
+#[derive(Debug, Clone)]
+pub struct Evaluation {
+    pub mode: TriggerMode,
+    pub checks: Vec<Check>,
+}
+
+impl Evaluation {
+    pub fn trigger(&self) -> Option<&Check> {
+        self.checks.iter().find(|check| check.matched && check.enabled)
+    }
+
+    pub fn should_respond(&self) -> bool {
+        self.trigger().is_some()
+    }
+}
+
+/// What the engine needs to know about a message beyond its text.
+#[derive(Debug, Clone, Default)]
+pub struct MessageFacts {
+    pub content: String,
+    pub is_dm: bool,
+    pub mentions_bot: bool,
+    pub mentions_bot_role: bool,
+    pub replies_to_bot: bool,
+    pub in_conversation: bool,
+}
+
+impl MessageFacts {
+    /// Facts about `msg`, sent in `guild_id`. Messages resolved for interactions
+    /// carry no guild, so it is passed separately. Role mentions count when the
+    /// role is the bot's own integration role or one the bot has.
+    pub fn from_message(ctx: &Context, msg: &Message, guild_id: Option<GuildId>, in_conversation: bool) -> Self {
+        let bot_id = ctx.cache.current_user().id;
+        let mentions_bot_role = !msg.mention_roles.is_empty() && guild_id
+            .and_then(|guild_id| ctx.cache.guild(guild_id))
+            .is_some_and(|guild| {
+                let bot_roles = guild.members.get(&bot_id).map(|member| member.roles.clone()).unwrap_or_default();
+                msg.mention_roles.iter().any(|role_id| {
+                    bot_roles.contains(role_id)
+                        || guild.roles.get(role_id).is_some_and(|role| role.tags.bot_id == Some(bot_id))
+                })
+            });
+
+        Self {
+            content: msg.content.clone(),
+            is_dm: guild_id.is_none(),
+            mentions_bot: msg.mentions_user_id(bot_id),
+            mentions_bot_role,
+            replies_to_bot: msg.referenced_message.as_ref().is_some_and(|referenced| referenced.author.id == bot_id),
+            in_conversation,
+        }
+    }
+}
+
+/// Decides whether a message is addressed to the bot. Names, aliases and help
+/// phrases only match as whole words, so "taxis" or "axis-aligned" do not wake
+/// it, while keywords of four or more letters also match their plurals and verb
+/// forms.
+pub struct TriggerEngine {
+    aliases: Vec<String>,
+}
+
+impl TriggerEngine {
+    /// `bot_name` always counts as an alias.
+    pub fn new(bot_name: &str, aliases: &[String]) -> Self {
+        let mut all: Vec<String> = Vec::new();
+        for alias in std::iter::once(bot_name).chain(aliases.iter().map(String::as_str)) {
+            let alias = alias.trim().to_lowercase();
+            if !alias.is_empty() && !all.contains(&alias) {
+                all.push(alias);
+            }
+        }
+        Self { aliases: all }
+    }
+
+    pub fn aliases(&self) -> &[String] {
+        &self.aliases
+    }
+
+    pub fn evaluate(&self, facts: &MessageFacts, mode: TriggerMode) -> Evaluation {
+        let content = facts.content.to_lowercase();
+        let flag = |rule, matched: bool, yes: &str, no: &str| check(mode, rule, matched, if matched { yes } else { no });
+
+        let alias = self.aliases.iter().find(|alias| contains_word(&content, alias));
+        let help = HELP_PATTERNS.iter().find(|pattern| contains_word(&content, pattern));
+        let keyword = DEV_KEYWORDS.iter().find(|keyword| contains_keyword(&content, keyword));
+        let error = traceback::parse(&facts.content);
+
+        let checks = vec![
+            flag(Rule::Conversation, facts.in_conversation, "The author is in a conversation with the bot here", "No conversation with the author here"),
+            flag(Rule::DirectMessage, facts.is_dm, "Sent in a DM, where the bot always answers", "Sent in a server"),
+            flag(Rule::UserMention, facts.mentions_bot, "The bot is @mentioned", "The bot is not @mentioned"),
+            flag(Rule::RoleMention, facts.mentions_bot_role, "A role the bot has is @mentioned", "No role of the bot is @mentioned"),
+            flag(Rule::ReplyToBot, facts.replies_to_bot, "Replies to one of the bot's messages", "Does not reply to the bot"),
+            match alias {
+                Some(alias) => check(mode, Rule::Alias, true, &format!("Contains \"{}\" as a word", alias)),
+                None => check(mode, Rule::Alias, false, &format!("None of {} appears as a whole word", self.alias_list())),
+            },
+            match &error {
+                Some(report) => check(mode, Rule::RobloxError, true, &format!("Parsed as {}", report.kind.label())),
+                None => check(mode, Rule::RobloxError, false, "No Roblox error or stack trace found"),
+            },
+            match (help, keyword) {
+                (Some(help), Some(keyword)) => check(mode, Rule::HelpRequest, true, &format!("Asks \"{}\" about \"{}\"", help, keyword)),
+                (Some(help), None) => check(mode, Rule::HelpRequest, false, &format!("Asks \"{}\" but mentions no Roblox keyword", help)),
+                (None, _) => check(mode, Rule::HelpRequest, false, "No help phrase such as \"how do i\""),
+            },
+            match keyword {
+                Some(keyword) if content.contains('?') && content.trim().len() > 10 => {
+                    check(mode, Rule::DevQuestion, true, &format!("A question mentioning \"{}\"", keyword))
+                }
+                Some(keyword) => check(mode, Rule::DevQuestion, false, &format!("Mentions \"{}\" but is not a question", keyword)),
+                None => check(mode, Rule::DevQuestion, false, "No Roblox keyword"),
+            },
+        ];
+
+        Evaluation { mode, checks }
+    }
+
+    fn alias_list(&self) -> String {
+        self.aliases.iter().map(|alias| format!("\"{}\"", alias)).collect::<Vec<_>>().join(", ")
+    }
+}
+
+fn check(mode: TriggerMode, rule: Rule, matched: bool, detail: &str) -> Check {
+    Check { rule, matched, enabled: rule.applies_in(mode), detail: detail.to_string() }
+}
+
+/// Letters, digits, hyphens and underscores join words, so "axis" is not a word
+/// of "taxis" or "axis-aligned".
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '-' || c == '_'
+}
+
+/// Endings that keep a keyword's meaning, so "scripts", "scripted" and
+/// "scripting" count as "script".
+const INFLECTIONS: &[&str] = &["", "s", "es", "d", "ed", "ing", "er", "ers"];
+
+/// Keywords shorter than this only match as whole words, since an ending turns
+/// them into unrelated words too easily.
+const MIN_INFLECTED_LEN: usize = 4;
+
+/// Whether `phrase` appears in `text` as a whole word. Used for the bot's names,
+/// where "taxis" must not count, and for help phrases. Both are expected in lowercase.
+fn contains_word(text: &str, phrase: &str) -> bool {
+    contains_with_endings(text, phrase, &[""])
+}
+
+/// Whether `keyword` appears in `text` as a word, or, when it is long enough,
+/// as a word ending in one of `INFLECTIONS`.
+fn contains_keyword(text: &str, keyword: &str) -> bool {
+    if keyword.len() < MIN_INFLECTED_LEN {
+        return contains_word(text, keyword);
+    }
+    contains_with_endings(text, keyword, INFLECTIONS)
+}
+
+fn contains_with_endings(text: &str, phrase: &str, endings: &[&str]) -> bool {
+    if phrase.is_empty() {
+        return false;
+    }
+    text.match_indices(phrase).any(|(start, _)| {
+        if text[..start].chars().next_back().is_some_and(is_word_char) {
+            return false;
+        }
+        let rest = &text[start + phrase.len()..];
+        endings.iter().any(|ending| {
+            rest.strip_prefix(ending)
+                .is_some_and(|after| !after.chars().next().is_some_and(is_word_char))
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn engine() -> TriggerEngine {
+        TriggerEngine::new("axis", &["ax".to_string(), "axis bot".to_string()])
+    }
+
+    fn facts(content: &str) -> MessageFacts {
+        MessageFacts { content: content.to_string(), ..Default::default() }
+    }
+
+    fn trigger(facts: &MessageFacts, mode: TriggerMode) -> Option<Rule> {
+        engine().evaluate(facts, mode).trigger().map(|check| check.rule)
+    }
+
+    #[test]
+    fn aliases_match_whole_words_only() {
+        let passive = TriggerMode::KeywordPassive;
+        assert_eq!(trigger(&facts("hey axis, you there"), passive), Some(Rule::Alias));
+        assert_eq!(trigger(&facts("AX can you look at this"), passive), Some(Rule::Alias));
+        assert_eq!(trigger(&facts("thanks axis bot"), passive), Some(Rule::Alias));
+        assert_eq!(trigger(&facts("we took taxis home"), passive), None);
+        assert_eq!(trigger(&facts("an axis-aligned box"), passive), None);
+    }
+
+    #[test]
+    fn long_keywords_match_inflections() {
+        let passive = TriggerMode::KeywordPassive;
+        assert_eq!(trigger(&facts("how do i fix my scripts"), passive), Some(Rule::HelpRequest));
+        assert_eq!(trigger(&facts("anyone good at scripting leaderstats?"), passive), Some(Rule::DevQuestion));
+        assert_eq!(trigger(&facts("why do my datastores keep failing?"), passive), Some(Rule::DevQuestion));
+    }
+
+    #[test]
+    fn short_keywords_and_help_phrases_match_whole_words_only() {
+        let passive = TriggerMode::KeywordPassive;
+        assert_eq!(trigger(&facts("how do i center a gui"), passive), Some(Rule::HelpRequest));
+        assert_eq!(trigger(&facts("what is going on with the guis today"), passive), None);
+        assert_eq!(trigger(&facts("can you tell me about roblox"), passive), Some(Rule::HelpRequest));
+        assert_eq!(trigger(&facts("he explained roblox physics"), passive), None);
+    }
+
+    #[test]
+    fn direct_messages_always_trigger() {
+        let dm = MessageFacts { is_dm: true, ..facts("hello there") };
+        assert_eq!(trigger(&dm, TriggerMode::KeywordPassive), Some(Rule::DirectMessage));
+        assert_eq!(trigger(&dm, TriggerMode::MentionOnly), Some(Rule::DirectMessage));
+    }
+
+    #[test]
+    fn role_mentions_trigger_in_every_mode() {
+        let mention = MessageFacts { mentions_bot_role: true, ..facts("<@&42> anyone around") };
+        assert_eq!(trigger(&mention, TriggerMode::KeywordPassive), Some(Rule::RoleMention));
+        assert_eq!(trigger(&mention, TriggerMode::MentionOnly), Some(Rule::RoleMention));
+    }
+
+    #[test]
+    fn mention_only_mode_ignores_passive_rules() {
+        let question = facts("axis, how do i make a remote event?");
+        assert_eq!(trigger(&question, TriggerMode::KeywordPassive), Some(Rule::Alias));
+        assert_eq!(trigger(&question, TriggerMode::MentionOnly), None);
+
+        let evaluation = engine().evaluate(&question, TriggerMode::MentionOnly);
+        let alias = evaluation.checks.iter().find(|check| check.rule == Rule::Alias).unwrap();
+        assert!(alias.matched && !alias.enabled);
+
+        let mention = MessageFacts { mentions_bot: true, ..question };
+        assert_eq!(trigger(&mention, TriggerMode::MentionOnly), Some(Rule::UserMention));
+    }
+}
@@ -1,18 +1,36 @@
 use serenity::async_trait;
-use serenity::builder::{CreateInteractionResponse, CreateInteractionResponseMessage};
+use serenity::builder::{CreateActionRow, CreateAttachment, CreateButton, CreateEmbed, CreateEmbedFooter, CreateInteractionResponse, CreateInteractionResponseFollowup, CreateInteractionResponseMessage, CreateMessage, CreateThread, EditAttachments, EditMessage, EditThread};
 use serenity::client::{Context, EventHandler};
+use serenity::http::Http;
 use serenity::model::gateway::Ready;
-use serenity::model::id::{ChannelId, UserId};
+use serenity::model::id::{ChannelId, GuildId, MessageId, UserId};
 use serenity::model::prelude::*;
 use serenity::prelude::*;
+use std::collections::HashMap;
 use std::sync::Arc;
 use dashmap::DashMap;
-use tracing::{error, info, debug};
+use tracing::{error, info, debug, warn};
 use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
 
-use crate::ai::GeminiClient;
+use crate::ai::history::{ConversationHistory, Turn};
+use crate::ai::attachments;
+use crate::ai::http::AiError;
+use crate::ai::postprocess;
+use crate::ai::intents::{Intent, IntentMatcher};
+use crate::ai::reply_chain;
+use crate::ai::traceback::{self, ErrorReport};
+use crate::ai::triggers::{MessageFacts, Rule, TriggerEngine};
+use crate::ai::providers::ChatMessage;
+use crate::ai::splitter::{self, DISCORD_MESSAGE_LIMIT};
+use crate::ai::cache::ResponseCache;
+use crate::ai::{AiClient, Answer, AnswerOptions, Detail};
 use crate::commands;
 use crate::config::Config;
+use crate::feedback::{FeedbackEntry, FeedbackStore, Rating};
+use crate::guild_settings::{ConversationMode, GuildSettingsStore};
+use crate::rag;
+use crate::usage::UsageTracker;
 
 pub struct ShardManagerContainer;
 
@@ -20,20 +38,52 @@ impl TypeMapKey for ShardManagerContainer {
     type Value = Arc<serenity::gateway::ShardManager>;
 }
 
+/// Minutes of inactivity after which a conversation ends.
+const CONVERSATION_TIMEOUT_MINUTES: u64 = 30;
+
+/// Conversations belong to one user in a channel, except for a channel's group
+/// session, which every participant shares.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ConversationKey {
+    User(ChannelId, UserId),
+    Group(ChannelId),
+}
+
+impl ConversationKey {
+    pub fn channel_id(&self) -> ChannelId {
+        match self {
+            ConversationKey::User(channel_id, _) | ConversationKey::Group(channel_id) => *channel_id,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct ConversationState {
-    pub user_id: UserId,
+    pub participants: Vec<UserId>,
     pub last_activity: Instant,
+    pub history: ConversationHistory,
+    /// Whether the bot opened a thread for this conversation, to be archived when it ends.
+    pub thread: bool,
 }
 
 impl ConversationState {
-    pub fn new(user_id: UserId) -> Self {
+    pub fn new(user_id: UserId, history: ConversationHistory) -> Self {
+        Self::group(vec![user_id], history)
+    }
+
+    pub fn group(participants: Vec<UserId>, history: ConversationHistory) -> Self {
         Self {
-            user_id,
+            participants,
             last_activity: Instant::now(),
+            history,
+            thread: false,
         }
     }
 
+    pub fn is_participant(&self, user_id: UserId) -> bool {
+        self.participants.contains(&user_id)
+    }
+
     pub fn update_activity(&mut self) {
         self.last_activity = Instant::now();
     }
@@ -43,26 +93,614 @@ impl ConversationState {
     }
 }
 
+async fn archive_thread(http: &Http, channel_id: ChannelId) {
+    if let Err(e) = channel_id.edit_thread(http, EditThread::new().archived(true)).await {
+        warn!("Failed to archive conversation thread {}: {}", channel_id, e);
+    }
+}
+
+/// Ends the conversation under `key`, archiving its thread once nobody else is
+/// talking in it. Returns whether there was a conversation to end.
+async fn end_conversation(conversations: &DashMap<ConversationKey, ConversationState>, http: &Http, key: ConversationKey) -> bool {
+    let Some((_, state)) = conversations.remove(&key) else {
+        return false;
+    };
+    let channel_id = key.channel_id();
+    if state.thread && !conversations.iter().any(|entry| entry.key().channel_id() == channel_id) {
+        archive_thread(http, channel_id).await;
+    }
+    true
+}
+
+/// Ends conversations that have been idle too long, archiving their threads.
+async fn expire_conversations(conversations: &DashMap<ConversationKey, ConversationState>, http: &Http) {
+    let expired: Vec<ConversationKey> = conversations.iter()
+        .filter(|entry| entry.value().is_expired(CONVERSATION_TIMEOUT_MINUTES))
+        .map(|entry| *entry.key())
+        .collect();
+
+    for key in expired {
+        if end_conversation(conversations, http, key).await {
+            debug!("Removed expired conversation {:?}", key);
+        }
+    }
+}
+
+/// Thread title for a conversation: the start of the question, or the asker's name.
+fn thread_name(msg: &Message) -> String {
+    let question: String = msg.content.lines().next().unwrap_or_default().trim().chars().take(90).collect();
+    if question.is_empty() {
+        format!("Conversation with {}", msg.author.global_name.as_ref().unwrap_or(&msg.author.name))
+    } else {
+        question
+    }
+}
+
+/// Sends `message` to `channel_id`, as a reply to `msg` when that is the channel it was posted in.
+async fn reply_in(ctx: &Context, msg: &Message, channel_id: ChannelId, message: CreateMessage) -> Result<Message, serenity::Error> {
+    let message = if channel_id == msg.channel_id { message.reference_message(msg) } else { message };
+    channel_id.send_message(&ctx.http, message).await
+}
+
+/// The conversation `user_id` is part of in `channel_id`: the channel's group
+/// session if they were added to it, otherwise their own.
+pub fn conversation_for(
+    conversations: &DashMap<ConversationKey, ConversationState>,
+    channel_id: ChannelId,
+    user_id: UserId,
+) -> Option<ConversationKey> {
+    let group = ConversationKey::Group(channel_id);
+    if conversations.get(&group).is_some_and(|state| state.is_participant(user_id)) {
+        return Some(group);
+    }
+    let own = ConversationKey::User(channel_id, user_id);
+    conversations.contains_key(&own).then_some(own)
+}
+
+/// Custom ID prefix of the buttons under AI answers.
+const ANSWER_BUTTON_PREFIX: &str = "answer:";
+
+/// Answers whose buttons keep working; older ones are forgotten first.
+const MAX_TRACKED_ANSWERS: usize = 500;
+
+/// Temperature for "Regenerate", high enough to get a noticeably different answer.
+const REGENERATE_TEMPERATURE: f32 = 0.9;
+
+const SHORTER_PROMPT: &str = "Rewrite your last answer to be much shorter. Keep only the essential explanation \
+    and code, and do not mention that it was shortened.";
+
+/// An answer posted with feedback buttons, kept so the buttons can act on it.
+#[derive(Debug, Clone)]
+pub struct AnswerRecord {
+    pub user_id: UserId,
+    pub guild_id: Option<GuildId>,
+    pub channel_id: ChannelId,
+    /// Conversation the answer belongs to, whose history follows a regenerated answer.
+    pub conversation: ConversationKey,
+    /// What the user wrote, without reply chain context, as logged with feedback.
+    pub prompt: String,
+    /// The message the model answered, including any reply chain context, for regenerating.
+    pub input: ChatMessage,
+    pub history: Vec<Turn>,
+    pub response: String,
+    /// Messages the answer is split across; the buttons sit on the last one.
+    pub message_ids: Vec<MessageId>,
+    pub votes: HashMap<UserId, Rating>,
+    pub created: Instant,
+}
+
+fn answer_buttons() -> Vec<CreateActionRow> {
+    let button = |action: &str| CreateButton::new(format!("{}{}", ANSWER_BUTTON_PREFIX, action)).style(ButtonStyle::Secondary);
+    vec![CreateActionRow::Buttons(vec![
+        button("up").emoji('👍'),
+        button("down").emoji('👎'),
+        button("regenerate").emoji('🔄').label("Regenerate"),
+        button("shorter").emoji('✂').label("Shorter"),
+    ])]
+}
+
+async fn reply_to_component(ctx: &Context, component: &ComponentInteraction, content: &str) -> Result<(), serenity::Error> {
+    let response = CreateInteractionResponse::Message(
+        CreateInteractionResponseMessage::new().content(content).ephemeral(true)
+    );
+    component.create_response(&ctx.http, response).await
+}
+
 pub struct Handler {
     pub config: Config,
-    pub gemini_client: GeminiClient,
-    pub active_conversations: Arc<DashMap<ChannelId, ConversationState>>,
+    pub ai_client: AiClient,
+    pub usage: Arc<UsageTracker>,
+    pub settings: Arc<GuildSettingsStore>,
+    pub cache: Arc<ResponseCache>,
+    pub active_conversations: Arc<DashMap<ConversationKey, ConversationState>>,
+    /// Recent answers by the message carrying their buttons.
+    pub answers: Arc<DashMap<MessageId, AnswerRecord>>,
+    pub feedback: Arc<FeedbackStore>,
+    pub intents: IntentMatcher,
+    pub triggers: TriggerEngine,
 }
 
 impl Handler {
     pub fn new(config: Config) -> Self {
         info!("Creating new Handler instance");
-        let gemini_client = GeminiClient::new(config.gemini_api_key.clone());
+        let usage = Arc::new(UsageTracker::load(
+            config.data_dir.join("usage.json"),
+            config.user_daily_token_quota,
+            config.guild_daily_token_quota,
+        ));
+        let settings = Arc::new(GuildSettingsStore::load(config.data_dir.join("guild_settings.json")));
+        let cache = Arc::new(ResponseCache::new(
+            config.response_cache_size,
+            Duration::from_secs(config.response_cache_ttl_secs),
+        ));
+        let ai_client = AiClient::new(
+            &config,
+            usage.clone(),
+            settings.clone(),
+            Arc::new(rag::load(&config.rag_index_path)),
+            cache.clone(),
+        );
+        let feedback = Arc::new(FeedbackStore::new(config.data_dir.join("feedback.jsonl")));
+        let intents = IntentMatcher::load(config.intent_patterns_path.as_deref());
+        let triggers = TriggerEngine::new(&config.bot_name, &config.bot_aliases);
         Self {
             config,
-            gemini_client,
+            ai_client,
+            usage,
+            settings,
+            cache,
             active_conversations: Arc::new(DashMap::new()),
+            answers: Arc::new(DashMap::new()),
+            feedback,
+            intents,
+            triggers,
+        }
+    }
+
+    fn fallback_message(e: &anyhow::Error) -> String {
+        match e.downcast_ref::<AiError>() {
+            Some(ai_error) => ai_error.user_message(),
+            None => "I'm having trouble processing your request right now.".to_string(),
+        }
+    }
+
+    fn split_for_discord(&self, response: &str) -> Vec<String> {
+        splitter::split_response(response, DISCORD_MESSAGE_LIMIT, self.config.max_reply_messages)
+    }
+
+    /// Messages `msg` replies to, directly or through earlier replies, oldest first
+    /// and at most `reply_chain_depth` of them.
+    async fn reply_chain(&self, ctx: &Context, msg: &Message) -> Vec<Message> {
+        let mut chain = Vec::new();
+        let mut next = msg.referenced_message.as_deref().cloned();
+
+        while let Some(message) = next.take() {
+            if chain.len() >= self.config.reply_chain_depth {
+                break;
+            }
+            // Discord only includes the first hop, so fetch the rest
+            let parent = message.message_reference.as_ref()
+                .and_then(|reference| reference.message_id.map(|id| (reference.channel_id, id)));
+            chain.push(message);
+            if let Some((channel_id, message_id)) = parent.filter(|_| chain.len() < self.config.reply_chain_depth) {
+                match channel_id.message(ctx, message_id).await {
+                    Ok(parent) => next = Some(parent),
+                    Err(e) => debug!("Reply chain stops at {}: {}", message_id, e),
+                }
+            }
+        }
+
+        chain.reverse();
+        chain
+    }
+
+    /// Remembers a posted answer so its buttons can act on it, forgetting the
+    /// oldest answer once `MAX_TRACKED_ANSWERS` are tracked.
+    fn track_answer(&self, record: AnswerRecord) {
+        let Some(&last) = record.message_ids.last() else {
+            return;
+        };
+        if self.answers.len() >= MAX_TRACKED_ANSWERS {
+            let oldest = self.answers.iter()
+                .min_by_key(|entry| entry.value().created)
+                .map(|entry| *entry.key());
+            if let Some(oldest) = oldest {
+                self.answers.remove(&oldest);
+            }
+        }
+        self.answers.insert(last, record);
+    }
+
+    /// Replaces a posted answer with `response`, editing its messages in place and
+    /// adding or deleting messages when the new answer splits differently.
+    async fn rewrite_answer(&self, ctx: &Context, mut record: AnswerRecord, answer: Answer) -> Result<(), serenity::Error> {
+        let processed = postprocess::extract_code_files(&answer.tagged(), self.config.code_attachment_threshold);
+        let parts = self.split_for_discord(&processed.text);
+        if parts.is_empty() {
+            error!("Rewritten AI response for user {} was empty", record.user_id);
+            return Ok(());
+        }
+
+        let mut files: Vec<CreateAttachment> = processed.files.into_iter()
+            .map(|file| CreateAttachment::bytes(file.contents.into_bytes(), file.name))
+            .collect();
+        let channel_id = record.channel_id;
+        let last = parts.len() - 1;
+        let mut message_ids = Vec::new();
+
+        for (index, part) in parts.iter().enumerate() {
+            let attached = if index == last { std::mem::take(&mut files) } else { Vec::new() };
+            let components = if index == last { answer_buttons() } else { Vec::new() };
+
+            let sent = match record.message_ids.get(index) {
+                Some(&message_id) => {
+                    // Replacing the attachments also drops files from the previous answer
+                    let attachments = attached.into_iter().fold(EditAttachments::new(), |attachments, file| attachments.add(file));
+                    let edit = EditMessage::new().content(part).components(components).attachments(attachments);
+                    channel_id.edit_message(&ctx.http, message_id, edit).await?
+                }
+                None => {
+                    let message = CreateMessage::new().content(part).add_files(attached).components(components);
+                    channel_id.send_message(&ctx.http, message).await?
+                }
+            };
+            message_ids.push(sent.id);
+        }
+        for &message_id in record.message_ids.iter().skip(parts.len()) {
+            if let Err(e) = channel_id.delete_message(&ctx.http, message_id).await {
+                debug!("Failed to delete leftover answer message {}: {}", message_id, e);
+            }
+        }
+
+        // Follow-up questions should build on the answer that is now on screen
+        if let Some(mut state) = self.active_conversations.get_mut(&record.conversation) {
+            state.history.replace_last_response(&record.response, &answer.text);
+        }
+
+        if let Some(previous) = record.message_ids.last() {
+            self.answers.remove(previous);
+        }
+        record.response = answer.text;
+        record.message_ids = message_ids;
+        record.votes.clear();
+        self.track_answer(record);
+        Ok(())
+    }
+
+    /// Handles the buttons under an answer: ratings go to the feedback log, while
+    /// "Regenerate" and "Shorter" rewrite the answer for the person who asked.
+    async fn answer_button(&self, ctx: &Context, component: &ComponentInteraction, action: &str) -> Result<(), serenity::Error> {
+        let message_id = component.message.id;
+        let Some(record) = self.answers.get(&message_id).map(|record| record.clone()) else {
+            return reply_to_component(ctx, component, "This answer is too old for its buttons to work. Ask again for a fresh one.").await;
+        };
+
+        let rating = match action {
+            "up" => Some(Rating::Up),
+            "down" => Some(Rating::Down),
+            _ => None,
+        };
+        if let Some(rating) = rating {
+            if record.votes.get(&component.user.id) == Some(&rating) {
+                return reply_to_component(ctx, component, "You already rated this answer.").await;
+            }
+
+            let entry = FeedbackEntry::new(component.user.id, record.guild_id, rating, self.ai_client.model(), &record.prompt, &record.response);
+            if let Err(e) = self.feedback.record(&entry).await {
+                error!("Failed to record feedback: {}", e);
+            }
+            if let Some(mut tracked) = self.answers.get_mut(&message_id) {
+                tracked.votes.insert(component.user.id, rating);
+            }
+
+            let thanks = match rating {
+                Rating::Up => "Thanks for the feedback! 👍",
+                Rating::Down => "Thanks for letting me know. **Regenerate** or **Shorter** may give a better answer.",
+            };
+            return reply_to_component(ctx, component, thanks).await;
+        }
+
+        if component.user.id != record.user_id {
+            return reply_to_component(ctx, component, "Only the person who asked can change this answer.").await;
+        }
+        if let Err(exceeded) = self.usage.check_quota(component.user.id, record.guild_id).await {
+            return reply_to_component(ctx, component, exceeded.user_message()).await;
+        }
+
+        let (input, history, options) = match action {
+            "regenerate" => {
+                let options = AnswerOptions { temperature: Some(REGENERATE_TEMPERATURE), detail: None };
+                (record.input.clone(), record.history.clone(), options)
+            }
+            "shorter" => {
+                let mut history = record.history.clone();
+                history.push(Turn::user(record.prompt.clone()));
+                history.push(Turn::model(record.response.clone()));
+                let options = AnswerOptions { temperature: None, detail: Some(Detail::Brief) };
+                (ChatMessage::user(SHORTER_PROMPT), history, options)
+            }
+            _ => return reply_to_component(ctx, component, "This button is no longer supported.").await,
+        };
+
+        // Acknowledge right away; the answer is edited once the new one is ready
+        component.create_response(&ctx.http, CreateInteractionResponse::Acknowledge).await?;
+        let _typing = record.channel_id.start_typing(&ctx.http);
+        info!("Running {} for answer {} from {}", action, message_id, component.user.tag());
+
+        match self.ai_client.generate_response_with(input, &history, options, &component.user, record.guild_id, ctx).await {
+            Ok(answer) => self.rewrite_answer(ctx, record, answer).await,
+            Err(e) => {
+                error!("Failed to {} answer: {}", action, e);
+                let followup = CreateInteractionResponseFollowup::new().content(Self::fallback_message(&e)).ephemeral(true);
+                component.create_followup(&ctx.http, followup).await.map(|_| ())
+            }
+        }
+    }
+
+    /// Answers a request the bot can handle itself, such as the ping or member
+    /// count, with the same embeds as the matching slash commands.
+    async fn answer_intent(&self, ctx: &Context, msg: &Message, intent: Intent) {
+        let embed = match intent {
+            Intent::CheckPing => {
+                let start = Instant::now();
+                let _ = msg.channel_id.broadcast_typing(&ctx.http).await;
+                let api_latency = start.elapsed().as_millis();
+                let gateway = commands::gateway_latency(ctx).await.ok().flatten();
+                Some(commands::ping_embed(api_latency, gateway))
+            }
+            Intent::CheckServerInfo => match msg.guild_id.and_then(|guild_id| commands::server_info(ctx, guild_id)) {
+                Some(info) => {
+                    let owner_tag = info.owner_id.to_user(&ctx.http).await.map_or("Unknown".to_string(), |u| u.tag());
+                    Some(commands::server_info_embed(info, owner_tag))
+                }
+                None => None,
+            },
+            Intent::CheckMemberCount => msg.guild_id
+                .and_then(|guild_id| commands::member_count(ctx, guild_id))
+                .map(|(guild_name, member_count)| commands::member_count_embed(guild_name, member_count)),
+            Intent::AskUsername => Some(
+                CreateEmbed::new()
+                    .title("🏷️ Your Name")
+                    .color(0x5865F2)
+                    .field("Username", &msg.author.name, true)
+                    .field("Display Name", msg.author.global_name.as_deref().unwrap_or("Not set"), true)
+            ),
+            Intent::AskNickname => msg.guild_id.map(|_| {
+                let nickname = msg.member.as_ref().and_then(|member| member.nick.as_deref());
+                CreateEmbed::new()
+                    .title("🏷️ Your Nickname")
+                    .color(0x5865F2)
+                    .description(nickname.unwrap_or("You don't have a nickname in this server."))
+            }),
+            Intent::AskUserId => Some(
+                CreateEmbed::new()
+                    .title("🆔 Your User ID")
+                    .color(0x5865F2)
+                    .description(format!("`{}`", msg.author.id))
+            ),
+            Intent::AskAvatar => Some(
+                CreateEmbed::new()
+                    .title("🖼️ Your Avatar")
+                    .color(0x5865F2)
+                    .image(msg.author.face())
+            ),
+            _ => return,
+        };
+
+        let message = match embed {
+            Some(embed) => CreateMessage::new().embed(embed.footer(CreateEmbedFooter::new("Axis Bot"))),
+            None => CreateMessage::new().content("That only works in a server."),
+        };
+        if let Err(e) = reply_in(ctx, msg, msg.channel_id, message).await {
+            error!("Failed to answer {:?} locally: {}", intent, e);
+        }
+    }
+
+    /// Answers a pasted Roblox error with an embed explaining it, from the built-in
+    /// explanations when there is one and from the model otherwise. The explanation
+    /// starts a conversation so the user can ask follow-up questions.
+    async fn explain_error(&self, ctx: &Context, msg: &Message, report: ErrorReport) {
+        info!("Explaining {} error from {}", report.kind.label(), msg.author.tag());
+        let thread = self.open_thread(ctx, msg).await;
+        let channel_id = thread.unwrap_or(msg.channel_id);
+        let _typing = channel_id.start_typing(&ctx.http);
+
+        let (shown, explanation, from_ai) = match traceback::canned_explanation(&report) {
+            Some(explanation) => (explanation.clone(), explanation, false),
+            None => {
+                let input = ChatMessage::user(traceback::prompt(&report, &msg.content));
+                match self.ai_client.generate_response(input, &[], &msg.author, msg.guild_id, ctx).await {
+                    Ok(answer) => (answer.tagged(), answer.text, true),
+                    Err(e) => {
+                        error!("Failed to explain error: {}", e);
+                        let _ = reply_in(ctx, msg, channel_id, CreateMessage::new().content(Self::fallback_message(&e))).await;
+                        return;
+                    }
+                }
+            }
+        };
+
+        let message = CreateMessage::new().embed(commands::error_embed(&report, &shown, from_ai));
+        if let Err(e) = reply_in(ctx, msg, channel_id, message).await {
+            error!("Failed to send error explanation: {}", e);
+            return;
+        }
+
+        let mut history = ConversationHistory::new(self.config.history_max_turns, self.config.history_char_budget);
+        history.push(Turn::user(msg.content.clone()));
+        history.push(Turn::model(explanation));
+        let mut state = ConversationState::new(msg.author.id, history);
+        state.thread = thread.is_some();
+        self.active_conversations.insert(ConversationKey::User(channel_id, msg.author.id), state);
+    }
+
+    /// Opens a thread for a conversation started by `msg` when the server uses a
+    /// thread mode. Returns `None` to keep the conversation in the channel.
+    async fn open_thread(&self, ctx: &Context, msg: &Message) -> Option<ChannelId> {
+        let guild_id = msg.guild_id?;
+        let mode = self.settings.get(Some(guild_id)).await.conversation_mode;
+        if mode == ConversationMode::Inline {
+            return None;
+        }
+
+        // Threads can only be opened in regular text and announcement channels
+        let kind = ctx.cache.guild(guild_id)
+            .and_then(|guild| guild.channels.get(&msg.channel_id).map(|channel| channel.kind))?;
+        if !matches!(kind, ChannelType::Text | ChannelType::News) {
+            return None;
+        }
+
+        let thread = CreateThread::new(thread_name(msg)).auto_archive_duration(AutoArchiveDuration::OneHour);
+        let result = match mode {
+            ConversationMode::PrivateThread => {
+                let thread = thread.kind(ChannelType::PrivateThread).invitable(false);
+                match msg.channel_id.create_thread(&ctx.http, thread).await {
+                    Ok(thread) => {
+                        if let Err(e) = thread.id.add_thread_member(&ctx.http, msg.author.id).await {
+                            warn!("Failed to add {} to private thread {}: {}", msg.author.tag(), thread.id, e);
+                        }
+                        let _ = msg.reply(&ctx.http, format!("I've opened a private thread for this: {}", thread.id.mention())).await;
+                        Ok(thread)
+                    }
+                    Err(e) => Err(e),
+                }
+            }
+            _ => msg.channel_id.create_thread_from_message(&ctx.http, msg.id, thread).await,
+        };
+
+        match result {
+            Ok(thread) => {
+                info!("Opened conversation thread {} for user {}", thread.id, msg.author.tag());
+                Some(thread.id)
+            }
+            Err(e) => {
+                warn!("Could not open a conversation thread, replying in the channel: {}", e);
+                None
+            }
         }
     }
 
-    fn has_active_conversation(&self, channel_id: ChannelId, user_id: UserId) -> bool {
-        self.active_conversations.get(&channel_id)
-            .map_or(false, |state| state.user_id == user_id)
+    /// Posts a finished answer. Long code blocks are pulled out into file attachments,
+    /// the remaining text is split into Discord-sized parts, and the first part goes
+    /// into `placeholder` when there is one or is sent as a reply otherwise. The files
+    /// and the feedback buttons ride along on the last message. Returns the messages
+    /// the answer was posted in.
+    async fn deliver(&self, ctx: &Context, msg: &Message, channel_id: ChannelId, placeholder: Option<&mut Message>, response: &str) -> Option<Vec<MessageId>> {
+        let processed = postprocess::extract_code_files(response, self.config.code_attachment_threshold);
+        let parts = self.split_for_discord(&processed.text);
+        if parts.is_empty() {
+            error!("AI response for user {} was empty", msg.author.tag());
+            let fallback = "I'm having trouble processing your request right now.";
+            let _ = match placeholder {
+                Some(reply) => reply.edit(&ctx.http, EditMessage::new().content(fallback)).await,
+                None => reply_in(ctx, msg, channel_id, CreateMessage::new().content(fallback)).await.map(|_| ()),
+            };
+            return None;
+        }
+
+        let mut files: Vec<CreateAttachment> = processed.files.into_iter()
+            .map(|file| CreateAttachment::bytes(file.contents.into_bytes(), file.name))
+            .collect();
+        let mut placeholder = placeholder;
+        let last = parts.len() - 1;
+        let mut message_ids = Vec::new();
+
+        for (index, part) in parts.iter().enumerate() {
+            let attached = if index == last { std::mem::take(&mut files) } else { Vec::new() };
+            let components = if index == last { answer_buttons() } else { Vec::new() };
+
+            let sent = match (index, placeholder.take()) {
+                (0, Some(reply)) => {
+                    let edit = attached.into_iter()
+                        .fold(EditMessage::new().content(part).components(components), |edit, file| edit.new_attachment(file));
+                    reply.edit(&ctx.http, edit).await.map(|_| reply.id)
+                }
+                (0, None) => {
+                    let message = CreateMessage::new().content(part).add_files(attached).components(components);
+                    reply_in(ctx, msg, channel_id, message).await.map(|sent| sent.id)
+                }
+                _ => {
+                    let message = CreateMessage::new().content(part).add_files(attached).components(components);
+                    channel_id.send_message(&ctx.http, message).await.map(|sent| sent.id)
+                }
+            };
+
+            match sent {
+                Ok(id) => message_ids.push(id),
+                Err(e) => {
+                    error!("Failed to send AI response part {}: {}", index + 1, e);
+                    return None;
+                }
+            }
+        }
+
+        Some(message_ids)
+    }
+
+    /// Generates the full answer, then replies with it. Returns the answer text,
+    /// without the Luau note, and the messages it was posted in.
+    async fn send_response(&self, ctx: &Context, msg: &Message, channel_id: ChannelId, input: &ChatMessage, history: &[Turn]) -> Option<(String, Vec<MessageId>)> {
+        match self.ai_client.generate_response(input.clone(), history, &msg.author, msg.guild_id, ctx).await {
+            Ok(answer) => {
+                debug!("Generated AI response for user {}", msg.author.tag());
+                let message_ids = self.deliver(ctx, msg, channel_id, None, &answer.tagged()).await?;
+                Some((answer.text, message_ids))
+            }
+            Err(e) => {
+                error!("Failed to generate AI response: {}", e);
+                let _ = reply_in(ctx, msg, channel_id, CreateMessage::new().content(Self::fallback_message(&e))).await;
+                None
+            }
+        }
+    }
+
+    /// Posts a placeholder reply and edits it as chunks of the answer stream in.
+    ///
+    /// Edits are throttled to `stream_edit_interval_ms` to stay clear of Discord's
+    /// message edit rate limits. Returns the answer text, without the Luau note, and
+    /// the messages it was posted in.
+    async fn stream_response(&self, ctx: &Context, msg: &Message, channel_id: ChannelId, input: &ChatMessage, history: &[Turn]) -> Option<(String, Vec<MessageId>)> {
+        let mut reply = match reply_in(ctx, msg, channel_id, CreateMessage::new().content("…")).await {
+            Ok(reply) => reply,
+            Err(e) => {
+                error!("Failed to send placeholder reply: {}", e);
+                return None;
+            }
+        };
+
+        let (tx, mut rx) = mpsc::unbounded_channel::<String>();
+        let edit_interval = Duration::from_millis(self.config.stream_edit_interval_ms);
+
+        let generation = self.ai_client.generate_response_stream(input.clone(), history, &msg.author, msg.guild_id, ctx, tx);
+        let editor = async {
+            let mut partial = String::new();
+            let mut last_edit = Instant::now();
+            while let Some(chunk) = rx.recv().await {
+                partial.push_str(&chunk);
+                if last_edit.elapsed() >= edit_interval {
+                    let preview: String = partial.chars().take(1990).collect();
+                    if let Err(e) = reply.edit(&ctx.http, EditMessage::new().content(format!("{} …", preview))).await {
+                        debug!("Failed to edit streamed reply: {}", e);
+                    }
+                    last_edit = Instant::now();
+                }
+            }
+        };
+
+        let (result, _) = tokio::join!(generation, editor);
+
+        match result {
+            Ok(answer) => {
+                debug!("Streamed AI response for user {}", msg.author.tag());
+                let message_ids = self.deliver(ctx, msg, channel_id, Some(&mut reply), &answer.tagged()).await?;
+                Some((answer.text, message_ids))
+            }
+            Err(e) => {
+                error!("Failed to stream AI response: {}", e);
+                let _ = reply.edit(&ctx.http, EditMessage::new().content(Self::fallback_message(&e))).await;
+                None
+            }
+        }
     }
 }
 
@@ -76,22 +714,12 @@ impl EventHandler for Handler {
 
         // Start background cleanup task
         let conversations = self.active_conversations.clone();
+        let http = ctx.http.clone();
         tokio::spawn(async move {
             let mut interval = tokio::time::interval(Duration::from_secs(300));
             loop {
                 interval.tick().await;
-                let mut to_remove = Vec::new();
-                
-                for entry in conversations.iter() {
-                    if entry.value().is_expired(30) {
-                        to_remove.push(*entry.key());
-                    }
-                }
-
-                for channel_id in to_remove {
-                    conversations.remove(&channel_id);
-                    debug!("Background cleanup: removed expired conversation in channel {}", channel_id);
-                }
+                expire_conversations(&conversations, &http).await;
             }
         });
     }
@@ -101,10 +729,23 @@ impl EventHandler for Handler {
             info!("Processing slash command: {} from user: {}", command.data.name, command.user.tag());
             
             let result = match command.data.name.as_str() {
+                commands::DEBUG_TRIGGER_COMMAND => {
+                    debug!("Executing debug trigger command");
+                    commands::debug_trigger(&ctx, &command, &self.triggers, &self.settings, &self.active_conversations).await
+                },
+                // Context-menu commands are named after their menu entry
+                name if command.data.kind == CommandType::Message => {
+                    debug!("Executing message command {}", name);
+                    commands::code_action(&ctx, &command, &self.ai_client, &self.usage, &self.config).await
+                },
                 "ping" => {
                     debug!("Executing ping command");
                     commands::ping(&ctx, &command).await
                 },
+                "ask" => {
+                    debug!("Executing ask command");
+                    commands::ask(&ctx, &command, &self.ai_client, &self.usage, &self.config).await
+                },
                 "serverinfo" => {
                     debug!("Executing serverinfo command");
                     commands::serverinfo(&ctx, &command).await
@@ -113,6 +754,30 @@ impl EventHandler for Handler {
                     debug!("Executing membercount command");
                     commands::membercount(&ctx, &command).await
                 },
+                "usage" => {
+                    debug!("Executing usage command");
+                    commands::usage(&ctx, &command, &self.usage).await
+                },
+                "settings" => {
+                    debug!("Executing settings command");
+                    commands::settings(&ctx, &command, &self.settings).await
+                },
+                "persona" => {
+                    debug!("Executing persona command");
+                    commands::persona(&ctx, &command, &self.settings).await
+                },
+                "summarize" => {
+                    debug!("Executing summarize command");
+                    commands::summarize(&ctx, &command, &self.ai_client, &self.usage).await
+                },
+                "cache" => {
+                    debug!("Executing cache command");
+                    commands::cache(&ctx, &command, &self.cache).await
+                },
+                "session" => {
+                    debug!("Executing session command");
+                    commands::session(&ctx, &command, &self.active_conversations, &self.config).await
+                },
                 unknown => {
                     error!("Unknown slash command: {}", unknown);
                     let response = CreateInteractionResponse::Message(
@@ -138,6 +803,20 @@ impl EventHandler for Handler {
                     let _ = command.create_response(&ctx.http, error_response).await;
                 }
             }
+        } else if let Interaction::Component(component) = interaction {
+            debug!("Processing component {} from user: {}", component.data.custom_id, component.user.tag());
+
+            let result = match component.data.custom_id.strip_prefix(ANSWER_BUTTON_PREFIX) {
+                Some(action) => self.answer_button(&ctx, &component, action).await,
+                None => {
+                    error!("Unknown component: {}", component.data.custom_id);
+                    reply_to_component(&ctx, &component, "This button is no longer supported.").await
+                }
+            };
+
+            if let Err(e) = result {
+                error!("Error processing component {}: {}", component.data.custom_id, e);
+            }
         }
     }
 
@@ -156,7 +835,9 @@ impl EventHandler for Handler {
             if let Some(guild_id) = msg.guild_id {
                 match guild_id.member(&ctx.http, msg.author.id).await {
                     Ok(member) => {
-                        if !member.permissions(&ctx.cache).map_or(false, |p| p.administrator()) {
+                        let is_admin = ctx.cache.guild(guild_id)
+                            .is_some_and(|guild| guild.member_permissions(&member).administrator());
+                        if !is_admin {
                             let _ = msg.reply(&ctx.http, "❌ You need Administrator permissions to sync commands.").await;
                             return;
                         }
@@ -173,11 +854,20 @@ impl EventHandler for Handler {
 
             let _ = msg.reply(&ctx.http, "🔄 Syncing commands...").await;
             
-            let register_commands = vec![
+            let mut register_commands = vec![
                 commands::register_ping(),
+                commands::register_ask(),
                 commands::register_serverinfo(),
                 commands::register_membercount(),
+                commands::register_usage(),
+                commands::register_summarize(),
+                commands::register_settings(),
+                commands::register_persona(),
+                commands::register_cache(),
+                commands::register_session(),
             ];
+            register_commands.extend(commands::register_code_actions());
+            register_commands.push(commands::register_debug_trigger());
             
             tokio::time::sleep(Duration::from_millis(500)).await;
             
@@ -195,81 +885,146 @@ impl EventHandler for Handler {
         }
 
         // Clean up expired conversations
-        let mut to_remove = Vec::new();
-        for entry in self.active_conversations.iter() {
-            if entry.value().is_expired(30) {
-                to_remove.push(*entry.key());
-            }
-        }
-        for channel_id in to_remove {
-            self.active_conversations.remove(&channel_id);
-        }
+        expire_conversations(&self.active_conversations, &ctx.http).await;
 
-        let has_active_convo = self.has_active_conversation(msg.channel_id, msg.author.id);
+        let active = conversation_for(&self.active_conversations, msg.channel_id, msg.author.id);
+        let has_active_convo = active.is_some();
+        let is_group = matches!(active, Some(ConversationKey::Group(_)));
 
         // Check if user wants to stop conversation
-        if has_active_convo && self.gemini_client.should_stop_conversation(&msg.content) {
-            self.active_conversations.remove(&msg.channel_id);
-            info!("Ended conversation with user {} in channel {}", msg.author.id, msg.channel_id);
-            let _ = msg.reply(&ctx.http, "Conversation ended. Feel free to reach out again if you need assistance with Roblox development.").await;
+        if let Some(key) = active.filter(|_| self.ai_client.should_stop_conversation(&msg.content)) {
+            end_conversation(&self.active_conversations, &ctx.http, key).await;
+            info!("Ended conversation {:?} at the request of user {}", key, msg.author.id);
+            let reply = if is_group {
+                "Group session ended. Feel free to reach out again if you need assistance with Roblox development."
+            } else {
+                "Conversation ended. Feel free to reach out again if you need assistance with Roblox development."
+            };
+            let _ = msg.reply(&ctx.http, reply).await;
             return;
         }
 
         // Update conversation activity if active
-        if has_active_convo {
-            if let Some(mut state) = self.active_conversations.get_mut(&msg.channel_id) {
-                if state.user_id == msg.author.id {
-                    state.update_activity();
-                }
+        if let Some(key) = active {
+            if let Some(mut state) = self.active_conversations.get_mut(&key) {
+                state.update_activity();
             }
         }
 
         // Determine if bot should respond
-        let should_respond = if has_active_convo {
-            true
-        } else {
-            self.gemini_client.should_respond_to_message(
-                &msg.content,
-                &self.config.bot_name,
-                msg.author.id,
-                msg.channel_id,
-                &Arc::new(DashMap::new()),
-            )
-        };
+        let facts = MessageFacts::from_message(&ctx, &msg, msg.guild_id, has_active_convo);
+        let trigger_mode = self.settings.get(msg.guild_id).await.trigger_mode;
+        let evaluation = self.triggers.evaluate(&facts, trigger_mode);
+        debug!("Trigger decision for message {}: {:?}", msg.id, evaluation.trigger().map(|check| check.rule));
+
+        if evaluation.should_respond() {
+            // Requests the bot can answer itself never reach the model. Inside a
+            // conversation, short follow-ups like "the name?" belong to the topic at
+            // hand, so they always go to the model and stay in its history
+            let in_conversation = evaluation.trigger().is_some_and(|check| check.rule == Rule::Conversation);
+            if msg.attachments.is_empty() && !in_conversation {
+                let local = self.intents.best(&msg.content, self.triggers.aliases())
+                    .filter(|score| score.intent.is_local() && score.confidence >= self.config.intent_min_confidence);
+                if let Some(score) = local {
+                    info!("Answering {:?} locally for {} (confidence {:.2})", score.intent, msg.author.tag(), score.confidence);
+                    self.answer_intent(&ctx, &msg, score.intent).await;
+                    return;
+                }
+            }
+
+            if let Err(exceeded) = self.usage.check_quota(msg.author.id, msg.guild_id).await {
+                info!("Daily quota ({:?}) reached for user {} in channel {}", exceeded, msg.author.tag(), msg.channel_id);
+                let _ = msg.reply(&ctx.http, exceeded.user_message()).await;
+                // A group session carries on for the other participants
+                if let Some(key) = active.filter(|_| !is_group) {
+                    self.active_conversations.remove(&key);
+                }
+                return;
+            }
+
+            // A pasted error outside a conversation gets a structured explanation
+            if active.is_none() {
+                if let Some(report) = traceback::parse(&msg.content) {
+                    self.explain_error(&ctx, &msg, report).await;
+                    return;
+                }
+            }
 
-        if should_respond {
             info!("Responding to message from {} in channel {}", msg.author.tag(), msg.channel_id);
             
-            // Start new conversation if needed
-            if !has_active_convo {
-                let state = ConversationState::new(msg.author.id);
-                self.active_conversations.insert(msg.channel_id, state);
-                info!("Started new conversation with user {} in channel {}", msg.author.id, msg.channel_id);
-            }
+            // Start new conversation if needed, in its own thread when the server asks for one
+            let key = match active {
+                Some(key) => key,
+                None => {
+                    let thread = self.open_thread(&ctx, &msg).await;
+                    let key = ConversationKey::User(thread.unwrap_or(msg.channel_id), msg.author.id);
+                    let history = ConversationHistory::new(self.config.history_max_turns, self.config.history_char_budget);
+                    let mut state = ConversationState::new(msg.author.id, history);
+                    state.thread = thread.is_some();
+                    self.active_conversations.insert(key, state);
+                    info!("Started new conversation with user {} in channel {}", msg.author.id, key.channel_id());
+                    key
+                }
+            };
+            let channel_id = key.channel_id();
 
             // Show typing indicator
-            let _typing = msg.channel_id.start_typing(&ctx.http);
+            let _typing = channel_id.start_typing(&ctx.http);
             
+            let history = self.active_conversations.get(&key)
+                .map(|state| state.history.turns())
+                .unwrap_or_default();
+
+            // Group sessions need to know who is speaking
+            let content = if is_group {
+                let name = msg.member.as_ref()
+                    .and_then(|member| member.nick.as_ref())
+                    .or(msg.author.global_name.as_ref())
+                    .unwrap_or(&msg.author.name);
+                format!("{}: {}", name, msg.content)
+            } else {
+                msg.content.clone()
+            };
+            let mut input = attachments::build_message(&content, &msg.attachments, self.config.attachment_max_bytes).await;
+            // History keeps the user's own words; the reply chain is only context for this answer
+            let user_text = input.text();
+            let chain = self.reply_chain(&ctx, &msg).await;
+            if !chain.is_empty() {
+                debug!("Including {} messages from the reply chain", chain.len());
+                reply_chain::prepend(&mut input, &chain, ctx.cache.current_user().id);
+            }
+
             // Generate AI response
-            match self.gemini_client.generate_response(&msg.content, &msg.author, msg.guild_id, &ctx).await {
-                Ok(response) => {
-                    debug!("Generated AI response for user {}", msg.author.tag());
-                    if let Err(e) = msg.reply(&ctx.http, response).await {
-                        error!("Failed to send AI response: {}", e);
-                        self.active_conversations.remove(&msg.channel_id);
+            let delivered = if self.config.stream_responses {
+                self.stream_response(&ctx, &msg, channel_id, &input, &history).await
+            } else {
+                self.send_response(&ctx, &msg, channel_id, &input, &history).await
+            };
+
+            match delivered {
+                Some((response, message_ids)) => {
+                    if let Some(mut state) = self.active_conversations.get_mut(&key) {
+                        state.history.push(Turn::user(user_text.clone()));
+                        state.history.push(Turn::model(response.clone()));
                     }
+                    self.track_answer(AnswerRecord {
+                        user_id: msg.author.id,
+                        guild_id: msg.guild_id,
+                        channel_id,
+                        conversation: key,
+                        prompt: user_text,
+                        input,
+                        history,
+                        response,
+                        message_ids,
+                        votes: HashMap::new(),
+                        created: Instant::now(),
+                    });
                 }
-                Err(e) => {
-                    error!("Failed to generate AI response: {}", e);
-                    let fallback = if e.to_string().contains("timeout") {
-                        "Request timed out. Please try again."
-                    } else {
-                        "I'm having trouble processing your request right now."
-                    };
-                    
-                    let _ = msg.reply(&ctx.http, fallback).await;
-                    self.active_conversations.remove(&msg.channel_id);
+                None if !is_group => {
+                    self.active_conversations.remove(&key);
                 }
+                None => {}
             }
         } else {
             debug!("Not responding to message from {}", msg.author.tag());
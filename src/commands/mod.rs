@@ -1,10 +1,53 @@
-use serenity::builder::{CreateCommand, CreateEmbed, CreateInteractionResponse, CreateInteractionResponseMessage, EditInteractionResponse};
+use serenity::builder::{CreateAttachment, CreateCommand, CreateCommandOption, CreateEmbed, CreateInteractionResponse, CreateInteractionResponseFollowup, CreateInteractionResponseMessage, CreateMessage, CreateThread, EditInteractionResponse, GetMessages};
 use serenity::model::prelude::*;
 use serenity::prelude::*;
+use dashmap::DashMap;
 use tracing::{info, error}; // Added error to tracing imports
 use crate::bot::ShardManagerContainer; // Added for ShardManagerContainer
+use crate::bot::{conversation_for, ConversationKey, ConversationState};
+use crate::ai::history::ConversationHistory;
+use crate::ai::cache::{CacheStats, ResponseCache};
+use crate::ai::http::AiError;
+use crate::ai::summarize::{self, Reference, Summary};
+use crate::ai::traceback::ErrorReport;
+use crate::ai::triggers::{MessageFacts, TriggerEngine};
+use crate::ai::postprocess;
+use crate::ai::splitter::{self, DISCORD_MESSAGE_LIMIT};
+use crate::ai::attachments;
+use crate::ai::code_actions::{self, CodeAction};
+use crate::ai::providers::ChatMessage;
+use crate::ai::{AiClient, AnswerOptions, Detail};
+use crate::ai::persona;
+use crate::ai::safety::{HarmCategory, SafetyThreshold};
+use crate::config::Config;
+use crate::guild_settings::{ConversationMode, GuildSettings, GuildSettingsStore, TriggerMode};
+use crate::usage::{TokenCounts, UsageTracker};
 // serenity::gateway::ShardManager import removed as it's not directly used.
-use serenity::model::id::ShardId; // Corrected ShardId import path
+
+/// Latency of the gateway heartbeat for the shard handling `ctx`, if known yet.
+pub async fn gateway_latency(ctx: &Context) -> Result<Option<std::time::Duration>, serenity::Error> {
+    let data_read = ctx.data.read().await;
+    // Retrieve the ShardManager from context data
+    let shard_manager_arc = data_read.get::<ShardManagerContainer>()
+        .cloned() // Clone the Arc<ShardManager>
+        .ok_or_else(|| {
+            error!("ShardManagerContainer not found in TypeMap");
+            serenity::Error::Other("ShardManagerContainer not found in TypeMap")
+        })?;
+    
+    // Lock the runners map
+    let runners_lock = shard_manager_arc.runners.lock().await;
+    
+    // Get the latency for the current shard
+    // ctx.shard_id is u64. The runners map uses u64 as keys directly.
+    let runner_info_opt = runners_lock.get(&ctx.shard_id);
+    info!("Shard ID: {}, Runner info found: {}", ctx.shard_id, runner_info_opt.is_some());
+
+    Ok(runner_info_opt.and_then(|runner| {
+        info!("Runner for shard {}: latency is {:?}", ctx.shard_id, runner.latency);
+        runner.latency
+    }))
+}
 
 pub async fn ping(ctx: &Context, command: &CommandInteraction) -> Result<(), serenity::Error> {
     info!("Ping command executed by {}", command.user.tag());
@@ -16,48 +59,82 @@ pub async fn ping(ctx: &Context, command: &CommandInteraction) -> Result<(), ser
     let duration = start.elapsed();
     let api_latency = duration.as_millis();
 
-    let ws_latency_str = {
-        let data_read = ctx.data.read().await;
-        // Retrieve the ShardManager from context data
-        let shard_manager_arc = data_read.get::<ShardManagerContainer>()
-            .cloned() // Clone the Arc<ShardManager>
-            .ok_or_else(|| {
-                error!("ShardManagerContainer not found in TypeMap");
-                serenity::Error::Other("ShardManagerContainer not found in TypeMap")
-            })?;
-        
-        // Lock the runners map
-        let runners_lock = shard_manager_arc.runners.lock().await;
-        
-        // Get the latency for the current shard
-        // ctx.shard_id is u64. The runners map uses u64 as keys directly.
-        let runner_info_opt = runners_lock.get(&ctx.shard_id);
-        info!("Shard ID: {}, Runner info found: {}", ctx.shard_id, runner_info_opt.is_some());
-
-        if let Some(runner) = runner_info_opt {
-            info!("Runner for shard {}: latency is {:?}", ctx.shard_id, runner.latency);
-            if let Some(latency_duration) = runner.latency {
-                format!("{}ms", latency_duration.as_millis())
-            } else {
-                "N/A".to_string()
-            }
-        } else {
-            "N/A".to_string()
-        }
+    let gateway = gateway_latency(ctx).await?;
+    info!("Ping result - API: {}ms, Gateway: {:?}", api_latency, gateway);
+
+    command.edit_response(&http, EditInteractionResponse::new().embed(ping_embed(api_latency, gateway))).await?;
+
+    Ok(())
+}
+
+pub fn ping_embed(api_latency_ms: u128, gateway: Option<std::time::Duration>) -> CreateEmbed {
+    let ws_latency_str = match gateway {
+        Some(latency_duration) => format!("{}ms", latency_duration.as_millis()),
+        None => "N/A".to_string(),
     };
-    
-    info!("Ping result - API: {}ms, Gateway: {}", api_latency, ws_latency_str);
 
-    let embed = CreateEmbed::new()
-        .field("API Latency", format!("{}ms", api_latency), true)
+    CreateEmbed::new()
+        .field("API Latency", format!("{}ms", api_latency_ms), true)
         .field("Gateway Latency", ws_latency_str, true)
-        .color(0x5865F2);
+        .color(0x5865F2)
+}
 
-    command.edit_response(&http, EditInteractionResponse::new().embed(embed)).await?;
+/// Snapshot of a guild's details, taken from the cache.
+pub struct ServerInfo {
+    pub name: String,
+    pub icon_url: String,
+    pub id: GuildId,
+    pub owner_id: UserId,
+    pub member_count: u64,
+    pub created_at: String,
+    pub role_count: usize,
+    pub channel_count: usize,
+    pub premium_tier: String,
+    pub boosters: u64,
+    pub verification_level: String,
+}
 
-    Ok(())
+pub fn server_info(ctx: &Context, guild_id: GuildId) -> Option<ServerInfo> {
+    let guild_ref = ctx.cache.guild(guild_id)?;
+    let owned_guild = (*guild_ref).clone();
+    let created_at = owned_guild.id.created_at();
+    Some(ServerInfo {
+        name: owned_guild.name.clone(),
+        icon_url: owned_guild.icon_url().unwrap_or_default(),
+        id: owned_guild.id,
+        owner_id: owned_guild.owner_id,
+        member_count: owned_guild.member_count,
+        created_at: format!("{}", created_at.date()),
+        role_count: owned_guild.roles.len(),
+        channel_count: owned_guild.channels.len(),
+        premium_tier: format!("{:?}", owned_guild.premium_tier),
+        boosters: owned_guild.premium_subscription_count.unwrap_or(0),
+        verification_level: format!("{:?}", owned_guild.verification_level),
+    })
+}
+
+pub fn server_info_embed(info: ServerInfo, owner_tag: String) -> CreateEmbed {
+    CreateEmbed::new()
+        .title(format!("📊 {}", info.name))
+        .color(0x5865F2)
+        .thumbnail(info.icon_url)
+        .field("👑 Owner", owner_tag, true)
+        .field("👥 Members", format!("{} members", info.member_count), true)
+        .field("📅 Created", info.created_at, true)
+        .field("🎭 Roles", info.role_count.to_string(), true)
+        .field("💬 Channels", info.channel_count.to_string(), true)
+        .field("🚀 Boost Level", info.premium_tier.replace("Tier", "Level"), true)
+        .field("💎 Boosters", info.boosters.to_string(), true)
+        .field("🔒 Verification", info.verification_level, true)
+        .field("🆔 Server ID", format!("`{}`", info.id), false)
+        .footer(serenity::builder::CreateEmbedFooter::new("Axis Bot"))
 }
 
+/// Guild name and member count, taken from the cache.
+pub fn member_count(ctx: &Context, guild_id: GuildId) -> Option<(String, u64)> {
+    let guild_ref = ctx.cache.guild(guild_id)?;
+    Some((guild_ref.name.clone(), guild_ref.member_count))
+}
 
 pub async fn serverinfo(ctx: &Context, command: &CommandInteraction) -> Result<(), serenity::Error> {
     let http = ctx.http.clone();
@@ -74,68 +151,17 @@ pub async fn serverinfo(ctx: &Context, command: &CommandInteraction) -> Result<(
         }
     };
 
-    type ServerInfoData = (String, String, String, UserId, String, String, String, String, String, String, String);
-    let guild_info_result: Result<ServerInfoData, ()> = {
-        match ctx.cache.guild(guild_id) {
-            Some(guild_ref) => {
-                let owned_guild = (*guild_ref).clone();
-                let created_at = owned_guild.id.created_at();
-                let created_date = format!("{}", created_at.date());
-                Ok((
-                    owned_guild.name.clone(),
-                    owned_guild.icon_url().unwrap_or_default(),
-                    owned_guild.id.to_string(),
-                    owned_guild.owner_id,
-                    owned_guild.member_count.to_string(),
-                    created_date,
-                    owned_guild.roles.len().to_string(),
-                    owned_guild.channels.len().to_string(),
-                    format!("{:?}", owned_guild.premium_tier),
-                    owned_guild.premium_subscription_count.unwrap_or(0).to_string(),
-                    format!("{:?}", owned_guild.verification_level),
-                ))
-            }
-            None => Err(()),
-        }
-    };
-
-    match guild_info_result {
-        Ok((
-            guild_name,
-            icon_url,
-            server_id_str,
-            owner_id,
-            member_count_str,
-            created_at_str,
-            roles_len_str,
-            channels_len_str,
-            premium_tier_str,
-            boosters_str,
-            verification_level_str,
-        )) => {
-            let owner_tag = owner_id.to_user(&http).await.map_or("Unknown".to_string(), |u| u.tag());
-            
-            let embed = CreateEmbed::new()
-                .title(format!("📊 {}", guild_name))
-                .color(0x5865F2)
-                .thumbnail(icon_url)
-                .field("👑 Owner", owner_tag, true)
-                .field("👥 Members", format!("{} members", member_count_str), true)
-                .field("📅 Created", created_at_str, true)
-                .field("🎭 Roles", roles_len_str, true)
-                .field("💬 Channels", channels_len_str, true)
-                .field("🚀 Boost Level", premium_tier_str.replace("Tier", "Level"), true)
-                .field("💎 Boosters", boosters_str, true)
-                .field("🔒 Verification", verification_level_str, true)
-                .field("🆔 Server ID", format!("`{}`", server_id_str), false)
-                .footer(serenity::builder::CreateEmbedFooter::new("Axis Bot"));
-            
+    match server_info(ctx, guild_id) {
+        Some(info) => {
+            let owner_tag = info.owner_id.to_user(&http).await.map_or("Unknown".to_string(), |u| u.tag());
+            let embed = server_info_embed(info, owner_tag);
+
             let response = CreateInteractionResponse::Message(
                 CreateInteractionResponseMessage::new().embed(embed)
             );
             command.create_response(&http, response).await?;
         }
-        Err(_) => {
+        None => {
             let err_response = CreateInteractionResponse::Message(
                 CreateInteractionResponseMessage::new()
                     .content("Could not fetch server information.")
@@ -163,32 +189,16 @@ pub async fn membercount(ctx: &Context, command: &CommandInteraction) -> Result<
         }
     };
 
-    let guild_data_result: Result<(String, u64), ()> = {
-        let guild_option = ctx.cache.guild(guild_id);
-        match guild_option {
-            Some(guild_ref) => {
-                let owned_guild = (*guild_ref).clone();
-                Ok((owned_guild.name.clone(), owned_guild.member_count))
-            }
-            None => Err(()),
-        }
-    };
-
-    match guild_data_result {
-        Ok((guild_name, member_count)) => {
-            let embed = CreateEmbed::new()
-                .title("👥 Member Statistics")
-                .color(0x57F287)
-                .field("🏠 Server", guild_name, false)
-                .field("📊 Total Members", format!("**{}** members", member_count), false)
-                .footer(serenity::builder::CreateEmbedFooter::new("Axis Bot • Member Count"));
+    match member_count(ctx, guild_id) {
+        Some((guild_name, member_count)) => {
+            let embed = member_count_embed(guild_name, member_count);
 
             let response = CreateInteractionResponse::Message(
                 CreateInteractionResponseMessage::new().embed(embed)
             );
             command.create_response(&http, response).await?;
         }
-        Err(_) => {
+        None => {
             let err_response = CreateInteractionResponse::Message(
                 CreateInteractionResponseMessage::new()
                     .content("Could not fetch server information for member count.")
@@ -201,10 +211,328 @@ pub async fn membercount(ctx: &Context, command: &CommandInteraction) -> Result<
     Ok(())
 }
 
+pub fn member_count_embed(guild_name: String, member_count: u64) -> CreateEmbed {
+    CreateEmbed::new()
+        .title("👥 Member Statistics")
+        .color(0x57F287)
+        .field("🏠 Server", guild_name, false)
+        .field("📊 Total Members", format!("**{}** members", member_count), false)
+        .footer(serenity::builder::CreateEmbedFooter::new("Axis Bot • Member Count"))
+}
+
+fn format_usage(counts: &TokenCounts) -> String {
+    format!(
+        "**{}** tokens ({} prompt / {} output) across {} requests",
+        counts.total(), counts.prompt_tokens, counts.output_tokens, counts.requests
+    )
+}
+
+fn format_quota(used: u64, quota: u64) -> String {
+    if quota == 0 {
+        "Unlimited".to_string()
+    } else {
+        format!("{} / {} tokens today", used.min(quota), quota)
+    }
+}
+
+fn is_admin(command: &CommandInteraction) -> bool {
+    command.member.as_ref()
+        .and_then(|member| member.permissions)
+        .is_some_and(|permissions| permissions.administrator())
+}
+
+/// Whether `user_id` owns the bot's application, alone or as a member of its team.
+async fn is_bot_owner(ctx: &Context, user_id: UserId) -> bool {
+    match ctx.http.get_current_application_info().await {
+        Ok(info) => {
+            info.owner.is_some_and(|owner| owner.id == user_id)
+                || info.team.is_some_and(|team| team.members.iter().any(|member| member.user.id == user_id))
+        }
+        Err(e) => {
+            error!("Failed to fetch application info: {}", e);
+            false
+        }
+    }
+}
+
+async fn reply_ephemeral(ctx: &Context, command: &CommandInteraction, content: &str) -> Result<(), serenity::Error> {
+    let response = CreateInteractionResponse::Message(
+        CreateInteractionResponseMessage::new().content(content).ephemeral(true)
+    );
+    command.create_response(&ctx.http, response).await
+}
+
+pub async fn usage(ctx: &Context, command: &CommandInteraction, tracker: &UsageTracker) -> Result<(), serenity::Error> {
+    let user_today = tracker.user_usage(command.user.id, 1).await;
+    let user_week = tracker.user_usage(command.user.id, 7).await;
+
+    let mut embed = CreateEmbed::new()
+        .title("📈 AI Usage")
+        .color(0x5865F2)
+        .field("Today", format_usage(&user_today), false)
+        .field("Last 7 Days", format_usage(&user_week), false)
+        .field("Your Daily Limit", format_quota(user_today.total(), tracker.user_daily_quota()), false);
+
+    if let (Some(guild_id), true) = (command.guild_id, is_admin(command)) {
+        let guild_today = tracker.guild_usage(guild_id, 1).await;
+        let guild_week = tracker.guild_usage(guild_id, 7).await;
+        embed = embed
+            .field("Server Today", format_usage(&guild_today), false)
+            .field("Server Last 7 Days", format_usage(&guild_week), false)
+            .field("Server Daily Limit", format_quota(guild_today.total(), tracker.guild_daily_quota()), false);
+    }
+
+    embed = embed.footer(serenity::builder::CreateEmbedFooter::new("Axis Bot • Days reset at 00:00 UTC"));
+
+    let response = CreateInteractionResponse::Message(
+        CreateInteractionResponseMessage::new().embed(embed).ephemeral(true)
+    );
+    command.create_response(&ctx.http, response).await
+}
+
 pub fn register_ping() -> CreateCommand {
     CreateCommand::new("ping").description("Check the bot's latency")
 }
 
+/// Replaces a deferred response with the user-facing message for a failed AI request.
+async fn edit_with_error(ctx: &Context, command: &CommandInteraction, what: &str, e: anyhow::Error) -> Result<(), serenity::Error> {
+    error!("Failed to answer {}: {}", what, e);
+    let message = e.downcast_ref::<AiError>()
+        .map(AiError::user_message)
+        .unwrap_or_else(|| "I'm having trouble processing your request right now.".to_string());
+    command.edit_response(&ctx.http, EditInteractionResponse::new().content(message)).await.map(|_| ())
+}
+
+/// Splits an answer into Discord-sized parts, moving long code blocks into files.
+fn answer_parts(answer: &str, config: &Config) -> (Vec<String>, Vec<CreateAttachment>) {
+    let processed = postprocess::extract_code_files(answer, config.code_attachment_threshold);
+    let parts = splitter::split_response(&processed.text, DISCORD_MESSAGE_LIMIT, config.max_reply_messages);
+    let files = processed.files.into_iter()
+        .map(|file| CreateAttachment::bytes(file.contents.into_bytes(), file.name))
+        .collect();
+    (parts, files)
+}
+
+/// Fills a deferred response with the first part and sends the rest as follow-ups,
+/// with the files on the last message.
+async fn send_deferred_answer(ctx: &Context, command: &CommandInteraction, parts: Vec<String>, mut files: Vec<CreateAttachment>, ephemeral: bool) -> Result<(), serenity::Error> {
+    if parts.is_empty() {
+        let edit = EditInteractionResponse::new().content("I'm having trouble processing your request right now.");
+        return command.edit_response(&ctx.http, edit).await.map(|_| ());
+    }
+
+    let last = parts.len() - 1;
+    for (index, part) in parts.into_iter().enumerate() {
+        let attached = if index == last { std::mem::take(&mut files) } else { Vec::new() };
+        if index == 0 {
+            let edit = attached.into_iter()
+                .fold(EditInteractionResponse::new().content(part), |edit, file| edit.new_attachment(file));
+            command.edit_response(&ctx.http, edit).await?;
+        } else {
+            let followup = CreateInteractionResponseFollowup::new().content(part).ephemeral(ephemeral).add_files(attached);
+            command.create_followup(&ctx.http, followup).await?;
+        }
+    }
+    Ok(())
+}
+
+/// `/ask question [private] [temperature] [detail]`: a one-off answer without typing a trigger phrase.
+pub async fn ask(ctx: &Context, command: &CommandInteraction, ai_client: &AiClient, usage: &UsageTracker, config: &Config) -> Result<(), serenity::Error> {
+    if let Err(exceeded) = usage.check_quota(command.user.id, command.guild_id).await {
+        return reply_ephemeral(ctx, command, exceeded.user_message()).await;
+    }
+
+    let options = command.data.options();
+    let question = string_option(&options, "question").unwrap_or_default().trim().to_string();
+    if question.is_empty() {
+        return reply_ephemeral(ctx, command, "❌ Please include a question.").await;
+    }
+    let private = options.iter()
+        .find_map(|option| match option.value {
+            ResolvedValue::Boolean(value) if option.name == "private" => Some(value),
+            _ => None,
+        })
+        .unwrap_or(false);
+    let answer_options = AnswerOptions {
+        temperature: options.iter().find_map(|option| match option.value {
+            ResolvedValue::Number(value) if option.name == "temperature" => Some(value as f32),
+            _ => None,
+        }),
+        detail: string_option(&options, "detail").and_then(Detail::from_key),
+    };
+
+    // Answers regularly take longer than the 3 second response window
+    if private {
+        command.defer_ephemeral(&ctx.http).await?;
+    } else {
+        command.defer(&ctx.http).await?;
+    }
+
+    info!("Answering /ask from {} (private: {})", command.user.tag(), private);
+    let answer = match ai_client.ask(ChatMessage::user(question), answer_options, &command.user, command.guild_id, ctx).await {
+        Ok(answer) => answer,
+        Err(e) => return edit_with_error(ctx, command, "/ask", e).await,
+    };
+
+    let (parts, files) = answer_parts(&answer.tagged(), config);
+    send_deferred_answer(ctx, command, parts, files, private).await
+}
+
+pub fn register_ask() -> CreateCommand {
+    CreateCommand::new("ask")
+        .description("Ask Axis a Roblox development question")
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::String, "question", "What you want to know")
+                .required(true)
+                .max_length(4000)
+        )
+        .add_option(CreateCommandOption::new(CommandOptionType::Boolean, "private", "Only show the answer to you"))
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::Number, "temperature", "Creativity from 0 (focused) to 1 (varied); default 0.3")
+                .min_number_value(0.0)
+                .max_number_value(1.0)
+        )
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::String, "detail", "How thorough the answer should be")
+                .add_string_choice("Brief", "brief")
+                .add_string_choice("Thorough", "thorough")
+        )
+}
+
+/// Thread on `message` for a long code explanation or review, reusing the message's
+/// thread when it already has one. `None` outside servers or when it cannot be created.
+async fn code_thread(ctx: &Context, command: &CommandInteraction, message: &Message, action: CodeAction) -> Option<ChannelId> {
+    command.guild_id?;
+    if let Some(thread) = &message.thread {
+        return Some(thread.id);
+    }
+
+    let name: String = format!("{} for {}", action.label(), message.author.global_name.as_ref().unwrap_or(&message.author.name))
+        .chars()
+        .take(100)
+        .collect();
+    let thread = CreateThread::new(name).auto_archive_duration(AutoArchiveDuration::OneDay);
+    match message.channel_id.create_thread_from_message(&ctx.http, message.id, thread).await {
+        Ok(thread) => Some(thread.id),
+        Err(e) => {
+            info!("Could not open a thread for a {}, replying privately: {}", action.label().to_lowercase(), e);
+            None
+        }
+    }
+}
+
+/// The "Explain code" and "Review code" message context-menu commands: runs the
+/// AI on the target message's code blocks and attachments. The answer is private,
+/// unless it is long enough to be posted in a thread on the message instead.
+pub async fn code_action(ctx: &Context, command: &CommandInteraction, ai_client: &AiClient, usage: &UsageTracker, config: &Config) -> Result<(), serenity::Error> {
+    let Some(action) = CodeAction::from_command_name(&command.data.name) else {
+        return reply_ephemeral(ctx, command, "Unknown command.").await;
+    };
+    let Some(ResolvedTarget::Message(message)) = command.data.target() else {
+        return reply_ephemeral(ctx, command, "❌ I couldn't read that message.").await;
+    };
+    if let Err(exceeded) = usage.check_quota(command.user.id, command.guild_id).await {
+        return reply_ephemeral(ctx, command, exceeded.user_message()).await;
+    }
+    let Some(prompt) = code_actions::prompt(action, message) else {
+        return reply_ephemeral(ctx, command, "❌ That message has no code or attachments to look at.").await;
+    };
+
+    command.defer_ephemeral(&ctx.http).await?;
+
+    info!("{} requested by {} for message {}", action.label(), command.user.tag(), message.id);
+    let input = attachments::build_message(&prompt, &message.attachments, config.attachment_max_bytes).await;
+    let options = AnswerOptions {
+        temperature: None,
+        detail: (action == CodeAction::Review).then_some(Detail::Thorough),
+    };
+    let answer = match ai_client.ask(input, options, &command.user, command.guild_id, ctx).await {
+        Ok(answer) => answer,
+        Err(e) => return edit_with_error(ctx, command, action.command_name(), e).await,
+    };
+
+    let (parts, mut files) = answer_parts(&answer.tagged(), config);
+    let long = parts.len() > 1 || !files.is_empty();
+    let thread = if long { code_thread(ctx, command, message, action).await } else { None };
+    let Some(thread) = thread else {
+        return send_deferred_answer(ctx, command, parts, files, true).await;
+    };
+
+    let last = parts.len() - 1;
+    for (index, part) in parts.into_iter().enumerate() {
+        let attached = if index == last { std::mem::take(&mut files) } else { Vec::new() };
+        thread.send_message(&ctx.http, CreateMessage::new().content(part).add_files(attached)).await?;
+    }
+    let edit = EditInteractionResponse::new().content(format!("📎 Posted the {} in {}.", action.label().to_lowercase(), thread.mention()));
+    command.edit_response(&ctx.http, edit).await.map(|_| ())
+}
+
+pub fn register_code_actions() -> Vec<CreateCommand> {
+    CodeAction::ALL.iter()
+        .map(|action| CreateCommand::new(action.command_name()).kind(CommandType::Message))
+        .collect()
+}
+
+/// Name of the message context-menu command that explains trigger decisions.
+pub const DEBUG_TRIGGER_COMMAND: &str = "Debug trigger";
+
+/// Shows which trigger rules a message matched and whether the bot would answer
+/// it, as if it had just been sent.
+pub async fn debug_trigger(
+    ctx: &Context,
+    command: &CommandInteraction,
+    triggers: &TriggerEngine,
+    store: &GuildSettingsStore,
+    conversations: &DashMap<ConversationKey, ConversationState>,
+) -> Result<(), serenity::Error> {
+    let Some(ResolvedTarget::Message(message)) = command.data.target() else {
+        return reply_ephemeral(ctx, command, "❌ I couldn't read that message.").await;
+    };
+    if message.author.bot {
+        return reply_ephemeral(ctx, command, "Messages from bots never trigger Axis.").await;
+    }
+
+    let in_conversation = conversation_for(conversations, message.channel_id, message.author.id).is_some();
+    let facts = MessageFacts::from_message(ctx, message, command.guild_id, in_conversation);
+    let mode = store.get(command.guild_id).await.trigger_mode;
+    let evaluation = triggers.evaluate(&facts, mode);
+
+    let result = match evaluation.trigger() {
+        Some(check) => format!("✅ Would respond: **{}**", check.rule.label()),
+        None => "❌ Would not respond".to_string(),
+    };
+    let checks = evaluation.checks.iter()
+        .map(|check| {
+            let icon = match (check.matched, check.enabled) {
+                (_, false) => "⏸️",
+                (true, true) => "✅",
+                (false, true) => "▫️",
+            };
+            format!("{} **{}:** {}", icon, check.rule.label(), check.detail)
+        })
+        .collect::<Vec<_>>();
+    let aliases = triggers.aliases().iter().map(|alias| format!("`{}`", alias)).collect::<Vec<_>>().join(", ");
+
+    info!("Trigger debug for message {} requested by {}", message.id, command.user.tag());
+    let embed = CreateEmbed::new()
+        .title("🔎 Trigger Check")
+        .color(0x5865F2)
+        .description(result)
+        .field("⚙️ Trigger Mode", evaluation.mode.label(), true)
+        .field("🏷️ Names", aliases, true)
+        .field("📋 Rules", truncate_field(checks), false)
+        .footer(serenity::builder::CreateEmbedFooter::new("Axis Bot • ⏸️ rules are off in mention-only mode"));
+
+    let response = CreateInteractionResponse::Message(
+        CreateInteractionResponseMessage::new().embed(embed).ephemeral(true)
+    );
+    command.create_response(&ctx.http, response).await
+}
+
+pub fn register_debug_trigger() -> CreateCommand {
+    CreateCommand::new(DEBUG_TRIGGER_COMMAND).kind(CommandType::Message)
+}
+
 pub fn register_serverinfo() -> CreateCommand {
     CreateCommand::new("serverinfo").description("Display information about the current server")
 }
@@ -212,3 +540,609 @@ pub fn register_serverinfo() -> CreateCommand {
 pub fn register_membercount() -> CreateCommand {
     CreateCommand::new("membercount").description("Display the current member count of the server")
 }
+
+pub fn register_usage() -> CreateCommand {
+    CreateCommand::new("usage").description("Show your AI token usage")
+}
+
+fn string_option<'a>(args: &[ResolvedOption<'a>], name: &str) -> Option<&'a str> {
+    args.iter().find_map(|arg| match arg.value {
+        ResolvedValue::String(value) if arg.name == name => Some(value),
+        _ => None,
+    })
+}
+
+fn settings_embed(settings: &GuildSettings) -> CreateEmbed {
+    let thresholds = HarmCategory::ALL.iter()
+        .map(|category| format!("**{}:** {}", category.label(), settings.safety.get(*category).label()))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    CreateEmbed::new()
+        .title("⚙️ Server Settings")
+        .color(0x5865F2)
+        .field("💬 Conversations", settings.conversation_mode.label(), false)
+        .field("📣 Triggers", settings.trigger_mode.label(), false)
+        .field("🛡️ Safety Filters", thresholds, false)
+        .footer(serenity::builder::CreateEmbedFooter::new("Axis Bot • Change with /settings"))
+}
+
+/// `/settings view`, `/settings safety`, `/settings conversations` and `/settings triggers`,
+/// restricted to administrators.
+pub async fn settings(ctx: &Context, command: &CommandInteraction, store: &GuildSettingsStore) -> Result<(), serenity::Error> {
+    let Some(guild_id) = command.guild_id else {
+        return reply_ephemeral(ctx, command, "❌ This command can only be used in servers.").await;
+    };
+    if !is_admin(command) {
+        return reply_ephemeral(ctx, command, "❌ You need Administrator permissions to change settings.").await;
+    }
+
+    let options = command.data.options();
+    let Some(subcommand) = options.first() else {
+        return reply_ephemeral(ctx, command, "Unknown subcommand.").await;
+    };
+    let ResolvedValue::SubCommand(args) = &subcommand.value else {
+        return reply_ephemeral(ctx, command, "Unknown subcommand.").await;
+    };
+
+    let settings = match subcommand.name {
+        "view" => store.get(Some(guild_id)).await,
+        "safety" => {
+            let (Some(category), Some(threshold)) = (
+                string_option(args, "category").and_then(HarmCategory::from_key),
+                string_option(args, "threshold").and_then(SafetyThreshold::from_key),
+            ) else {
+                return reply_ephemeral(ctx, command, "❌ Unknown category or threshold.").await;
+            };
+
+            info!("Setting {} safety threshold to {} in guild {}", category.key(), threshold.key(), guild_id);
+            store.update(guild_id, |settings| settings.safety.set(category, threshold)).await
+        }
+        "conversations" => {
+            let Some(mode) = string_option(args, "mode").and_then(ConversationMode::from_key) else {
+                return reply_ephemeral(ctx, command, "❌ Unknown conversation mode.").await;
+            };
+
+            info!("Setting conversation mode to {} in guild {}", mode.key(), guild_id);
+            store.update(guild_id, |settings| settings.conversation_mode = mode).await
+        }
+        "triggers" => {
+            let Some(mode) = string_option(args, "mode").and_then(TriggerMode::from_key) else {
+                return reply_ephemeral(ctx, command, "❌ Unknown trigger mode.").await;
+            };
+
+            info!("Setting trigger mode to {} in guild {}", mode.key(), guild_id);
+            store.update(guild_id, |settings| settings.trigger_mode = mode).await
+        }
+        _ => return reply_ephemeral(ctx, command, "Unknown subcommand.").await,
+    };
+
+    let response = CreateInteractionResponse::Message(
+        CreateInteractionResponseMessage::new().embed(settings_embed(&settings)).ephemeral(true)
+    );
+    command.create_response(&ctx.http, response).await
+}
+
+pub fn register_settings() -> CreateCommand {
+    let category = HarmCategory::ALL.iter().fold(
+        CreateCommandOption::new(CommandOptionType::String, "category", "Harm category to configure").required(true),
+        |option, category| option.add_string_choice(category.label(), category.key()),
+    );
+    let threshold = SafetyThreshold::ALL.iter().fold(
+        CreateCommandOption::new(CommandOptionType::String, "threshold", "Lowest probability of harm that gets blocked").required(true),
+        |option, threshold| option.add_string_choice(threshold.label(), threshold.key()),
+    );
+    let mode = ConversationMode::ALL.iter().fold(
+        CreateCommandOption::new(CommandOptionType::String, "mode", "Where new conversations take place").required(true),
+        |option, mode| option.add_string_choice(mode.label(), mode.key()),
+    );
+    let trigger_mode = TriggerMode::ALL.iter().fold(
+        CreateCommandOption::new(CommandOptionType::String, "mode", "Which messages Axis answers outside a conversation").required(true),
+        |option, mode| option.add_string_choice(mode.label(), mode.key()),
+    );
+
+    CreateCommand::new("settings")
+        .description("Configure Axis for this server")
+        .default_member_permissions(Permissions::ADMINISTRATOR)
+        .dm_permission(false)
+        .add_option(CreateCommandOption::new(CommandOptionType::SubCommand, "view", "Show the current server settings"))
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::SubCommand, "safety", "Set the AI safety filter threshold for a category")
+                .add_sub_option(category)
+                .add_sub_option(threshold)
+        )
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::SubCommand, "conversations", "Reply inline or open a thread for each conversation")
+                .add_sub_option(mode)
+        )
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::SubCommand, "triggers", "Answer only @mentions, or also names, errors and Roblox questions")
+                .add_sub_option(trigger_mode)
+        )
+}
+
+fn persona_embed(persona: Option<&str>) -> CreateEmbed {
+    let (status, template) = match persona {
+        Some(template) => ("Custom persona", template),
+        None => ("Built-in default", persona::DEFAULT_PERSONA),
+    };
+    let variables = persona::TEMPLATE_VARIABLES.iter()
+        .map(|(name, meaning)| format!("`{}` - {}", name, meaning))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    // Keep fences inside the prompt from closing the embed's code block, and
+    // stay within the 4096 character description limit
+    let template: String = template.replace("```", "`\u{200b}``").chars().take(4080).collect();
+
+    CreateEmbed::new()
+        .title(format!("🎭 Persona • {}", status))
+        .color(0x5865F2)
+        .description(format!("```\n{}\n```", template))
+        .field("🧩 Template Variables", variables, false)
+        .footer(serenity::builder::CreateEmbedFooter::new("Axis Bot • Type \\n for line breaks in /persona set"))
+}
+
+/// `/persona set`, `/persona view` and `/persona reset`, restricted to administrators.
+pub async fn persona(ctx: &Context, command: &CommandInteraction, store: &GuildSettingsStore) -> Result<(), serenity::Error> {
+    let Some(guild_id) = command.guild_id else {
+        return reply_ephemeral(ctx, command, "❌ This command can only be used in servers.").await;
+    };
+    if !is_admin(command) {
+        return reply_ephemeral(ctx, command, "❌ You need Administrator permissions to change the persona.").await;
+    }
+
+    let options = command.data.options();
+    let Some(subcommand) = options.first() else {
+        return reply_ephemeral(ctx, command, "Unknown subcommand.").await;
+    };
+    let ResolvedValue::SubCommand(args) = &subcommand.value else {
+        return reply_ephemeral(ctx, command, "Unknown subcommand.").await;
+    };
+
+    let settings = match subcommand.name {
+        "view" => store.get(Some(guild_id)).await,
+        "set" => {
+            let prompt = persona::normalize(string_option(args, "prompt").unwrap_or_default());
+            if prompt.is_empty() {
+                return reply_ephemeral(ctx, command, "❌ The persona prompt cannot be empty.").await;
+            }
+            if prompt.chars().count() > persona::MAX_PERSONA_CHARS {
+                let message = format!("❌ The persona prompt is limited to {} characters.", persona::MAX_PERSONA_CHARS);
+                return reply_ephemeral(ctx, command, &message).await;
+            }
+
+            info!("Setting custom persona ({} chars) in guild {}", prompt.chars().count(), guild_id);
+            store.update(guild_id, |settings| settings.persona = Some(prompt)).await
+        }
+        "reset" => {
+            info!("Resetting persona in guild {}", guild_id);
+            store.update(guild_id, |settings| settings.persona = None).await
+        }
+        _ => return reply_ephemeral(ctx, command, "Unknown subcommand.").await,
+    };
+
+    let response = CreateInteractionResponse::Message(
+        CreateInteractionResponseMessage::new().embed(persona_embed(settings.persona.as_deref())).ephemeral(true)
+    );
+    command.create_response(&ctx.http, response).await
+}
+
+pub fn register_persona() -> CreateCommand {
+    CreateCommand::new("persona")
+        .description("Customize how Axis presents itself in this server")
+        .default_member_permissions(Permissions::ADMINISTRATOR)
+        .dm_permission(false)
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::SubCommand, "set", "Replace the system prompt for this server")
+                .add_sub_option(
+                    CreateCommandOption::new(CommandOptionType::String, "prompt", "System prompt; supports {bot_name}, {user_info}, {username} and {display_name}")
+                        .required(true)
+                        .max_length(persona::MAX_PERSONA_CHARS as u16)
+                )
+        )
+        .add_option(CreateCommandOption::new(CommandOptionType::SubCommand, "view", "Show the current system prompt"))
+        .add_option(CreateCommandOption::new(CommandOptionType::SubCommand, "reset", "Go back to the built-in persona"))
+}
+
+fn cache_embed(stats: &CacheStats, purged: Option<usize>) -> CreateEmbed {
+    let mut embed = CreateEmbed::new()
+        .title("🗃️ Response Cache")
+        .color(0x5865F2);
+
+    if let Some(purged) = purged {
+        embed = embed.description(format!("Purged {} cached responses.", purged));
+    }
+    if stats.capacity == 0 {
+        return embed.field("Status", "Disabled (`RESPONSE_CACHE_SIZE=0`)", false);
+    }
+
+    embed
+        .field("📦 Entries", format!("{} / {}", stats.entries, stats.capacity), true)
+        .field("⏳ TTL", format!("{} min", stats.ttl.as_secs() / 60), true)
+        .field("🎯 Hit Rate", format!("{:.1}%", stats.hit_rate() * 100.0), true)
+        .field("✅ Hits", stats.hits.to_string(), true)
+        .field("❌ Misses", stats.misses.to_string(), true)
+        .footer(serenity::builder::CreateEmbedFooter::new("Axis Bot • Counters reset when the bot restarts"))
+}
+
+/// `/cache stats` and `/cache purge`, restricted to the bot's owner because the
+/// cache is shared by every server.
+pub async fn cache(ctx: &Context, command: &CommandInteraction, cache: &ResponseCache) -> Result<(), serenity::Error> {
+    if !is_bot_owner(ctx, command.user.id).await {
+        return reply_ephemeral(ctx, command, "❌ Only the bot's owner can manage the cache, which every server shares.").await;
+    }
+
+    let options = command.data.options();
+    let purged = match options.first().map(|subcommand| subcommand.name) {
+        Some("stats") => None,
+        Some("purge") => {
+            let purged = cache.purge();
+            info!("{} purged {} cached responses", command.user.tag(), purged);
+            Some(purged)
+        }
+        _ => return reply_ephemeral(ctx, command, "Unknown subcommand.").await,
+    };
+
+    let response = CreateInteractionResponse::Message(
+        CreateInteractionResponseMessage::new().embed(cache_embed(&cache.stats(), purged)).ephemeral(true)
+    );
+    command.create_response(&ctx.http, response).await
+}
+
+pub fn register_cache() -> CreateCommand {
+    CreateCommand::new("cache")
+        .description("Inspect or clear the AI response cache shared by every server (bot owner only)")
+        .default_member_permissions(Permissions::ADMINISTRATOR)
+        .dm_permission(false)
+        .add_option(CreateCommandOption::new(CommandOptionType::SubCommand, "stats", "Show cache size and hit rate"))
+        .add_option(CreateCommandOption::new(CommandOptionType::SubCommand, "purge", "Remove every cached answer"))
+}
+
+/// Most messages `/summarize` will read.
+const MAX_SUMMARY_MESSAGES: usize = 200;
+
+/// Fetches up to `count` messages from the channel, newest first, stopping at
+/// messages older than `since`.
+async fn recent_messages(ctx: &Context, channel_id: ChannelId, count: usize, since: Option<i64>) -> Result<Vec<Message>, serenity::Error> {
+    let mut messages: Vec<Message> = Vec::new();
+
+    while messages.len() < count {
+        let mut request = GetMessages::new().limit((count - messages.len()).min(100) as u8);
+        if let Some(oldest) = messages.last() {
+            request = request.before(oldest.id);
+        }
+        let page = channel_id.messages(&ctx.http, request).await?;
+        let exhausted = page.is_empty();
+
+        for message in page {
+            if since.is_some_and(|since| message.timestamp.unix_timestamp() < since) {
+                return Ok(messages);
+            }
+            messages.push(message);
+        }
+        if exhausted {
+            break;
+        }
+    }
+
+    Ok(messages)
+}
+
+/// Joins `lines` into an embed field value within Discord's 1024 byte limit.
+/// Lines that no longer fit are dropped, except the first, which is cut short
+/// so the field is never empty.
+fn truncate_field(lines: Vec<String>) -> String {
+    const FIELD_LIMIT: usize = 1024;
+
+    let mut value = String::new();
+    for line in lines {
+        if value.len() + line.len() + 1 > FIELD_LIMIT {
+            if value.is_empty() {
+                let mut end = FIELD_LIMIT - "…".len();
+                while !line.is_char_boundary(end) {
+                    end -= 1;
+                }
+                value.push_str(&line[..end]);
+                value.push('…');
+            }
+            break;
+        }
+        value.push_str(&line);
+        value.push('\n');
+    }
+
+    if value.trim().is_empty() {
+        return "Nothing to show.".to_string();
+    }
+    value
+}
+
+/// Most characters Discord accepts across an embed's title, description,
+/// fields and footer combined.
+const EMBED_TOTAL_LIMIT: usize = 6000;
+
+/// `description` cut to the 4096 characters Discord allows, and to what is left
+/// of the embed's total once `other_parts` (title, field names and values,
+/// footer) are counted.
+fn fit_description(description: &str, other_parts: &[&str]) -> String {
+    let used: usize = other_parts.iter().map(|part| part.chars().count()).sum();
+    let budget = EMBED_TOTAL_LIMIT.saturating_sub(used).min(4096);
+    description.chars().take(budget).collect()
+}
+
+fn summary_embed(summary: &Summary, channel_id: ChannelId, guild_id: Option<GuildId>, message_count: usize) -> CreateEmbed {
+    let reference_lines = |references: &[Reference]| references.iter()
+        .map(|reference| match reference.message_id {
+            Some(id) => format!("• {} ([jump]({}))", reference.description, id.link(channel_id, guild_id)),
+            None => format!("• {}", reference.description),
+        })
+        .collect::<Vec<_>>();
+
+    let key_points = if summary.key_points.is_empty() {
+        "Nothing notable was discussed.".to_string()
+    } else {
+        summary.key_points.iter().map(|point| format!("• {}", point)).collect::<Vec<_>>().join("\n")
+    };
+
+    let mut fields = Vec::new();
+    if !summary.code_solutions.is_empty() {
+        fields.push(("💡 Code Solutions", truncate_field(reference_lines(&summary.code_solutions))));
+    }
+    if !summary.relevant_messages.is_empty() {
+        fields.push(("🔗 Relevant Messages", truncate_field(reference_lines(&summary.relevant_messages))));
+    }
+
+    let title = "📝 Channel Summary";
+    let footer = format!("Axis Bot • Summarized the last {} messages", message_count);
+    let mut parts = vec![title, footer.as_str()];
+    parts.extend(fields.iter().flat_map(|(name, value)| [*name, value.as_str()]));
+
+    CreateEmbed::new()
+        .title(title)
+        .color(0x5865F2)
+        .description(fit_description(&key_points, &parts))
+        .fields(fields.into_iter().map(|(name, value)| (name, value, false)))
+        .footer(serenity::builder::CreateEmbedFooter::new(footer))
+}
+
+/// `/summarize [messages] [minutes]`: summarizes recent discussion in the channel.
+pub async fn summarize(ctx: &Context, command: &CommandInteraction, ai_client: &AiClient, usage: &UsageTracker) -> Result<(), serenity::Error> {
+    if let Err(exceeded) = usage.check_quota(command.user.id, command.guild_id).await {
+        return reply_ephemeral(ctx, command, exceeded.user_message()).await;
+    }
+
+    let options = command.data.options();
+    let integer = |name: &str| options.iter().find_map(|option| match option.value {
+        ResolvedValue::Integer(value) if option.name == name => Some(value),
+        _ => None,
+    });
+    let minutes = integer("minutes");
+    let count = integer("messages")
+        .map(|count| count as usize)
+        .unwrap_or(if minutes.is_some() { MAX_SUMMARY_MESSAGES } else { 50 });
+    let since = minutes.map(|minutes| Timestamp::now().unix_timestamp() - minutes * 60);
+
+    // Fetching history and generating the summary can take longer than the 3 second response window
+    command.defer(&ctx.http).await?;
+
+    let messages = recent_messages(ctx, command.channel_id, count, since).await?;
+    let (transcript, message_count) = summarize::transcript(&messages);
+    if message_count == 0 {
+        let edit = EditInteractionResponse::new().content("There are no recent messages to summarize.");
+        return command.edit_response(&ctx.http, edit).await.map(|_| ());
+    }
+
+    info!("Summarizing {} messages in channel {} for {}", message_count, command.channel_id, command.user.tag());
+
+    let known: Vec<MessageId> = messages.iter().map(|message| message.id).collect();
+    let edit = match ai_client.summarize(&transcript, &known, &command.user, command.guild_id).await {
+        Ok(summary) => EditInteractionResponse::new()
+            .embed(summary_embed(&summary, command.channel_id, command.guild_id, message_count)),
+        Err(e) => {
+            error!("Failed to summarize channel {}: {}", command.channel_id, e);
+            let message = e.downcast_ref::<AiError>()
+                .map(AiError::user_message)
+                .unwrap_or_else(|| "I couldn't summarize this channel right now.".to_string());
+            EditInteractionResponse::new().content(message)
+        }
+    };
+    command.edit_response(&ctx.http, edit).await.map(|_| ())
+}
+
+pub fn register_summarize() -> CreateCommand {
+    CreateCommand::new("summarize")
+        .description("Summarize the recent discussion in this channel")
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::Integer, "messages", "How many recent messages to read (default 50)")
+                .min_int_value(5)
+                .max_int_value(MAX_SUMMARY_MESSAGES as u64)
+        )
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::Integer, "minutes", "Only read messages from the last this many minutes")
+                .min_int_value(1)
+                .max_int_value(1440)
+        )
+}
+
+/// Most people a group session can hold.
+const MAX_SESSION_PARTICIPANTS: usize = 10;
+
+fn session_embed(state: &ConversationState, title: &str) -> CreateEmbed {
+    let participants = state.participants.iter()
+        .map(|user_id| user_id.mention().to_string())
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    CreateEmbed::new()
+        .title(format!("👥 {}", title))
+        .color(0x5865F2)
+        .description("Participants can talk to me here without the trigger phrase, and everyone shares one conversation.")
+        .field("🙋 Participants", participants, false)
+        .field("💬 Turns", state.history.turns().len().to_string(), true)
+        .footer(serenity::builder::CreateEmbedFooter::new("Axis Bot • Ends after 30 minutes of inactivity or with /session end"))
+}
+
+fn user_option(args: &[ResolvedOption], name: &str) -> Option<UserId> {
+    args.iter().find_map(|arg| match arg.value {
+        ResolvedValue::User(user, _) if arg.name == name => Some(user.id),
+        _ => None,
+    })
+}
+
+/// `/session start`, `add`, `remove`, `view` and `end`: a group conversation in
+/// the current channel shared by several named participants.
+pub async fn session(
+    ctx: &Context,
+    command: &CommandInteraction,
+    conversations: &DashMap<ConversationKey, ConversationState>,
+    config: &Config,
+) -> Result<(), serenity::Error> {
+    if command.guild_id.is_none() {
+        return reply_ephemeral(ctx, command, "❌ This command can only be used in servers.").await;
+    }
+
+    let options = command.data.options();
+    let Some(subcommand) = options.first() else {
+        return reply_ephemeral(ctx, command, "Unknown subcommand.").await;
+    };
+    let ResolvedValue::SubCommand(args) = &subcommand.value else {
+        return reply_ephemeral(ctx, command, "Unknown subcommand.").await;
+    };
+
+    let key = ConversationKey::Group(command.channel_id);
+    if subcommand.name == "start" {
+        if conversations.contains_key(&key) {
+            return reply_ephemeral(ctx, command, "❌ This channel already has a group session. Use `/session view` to see who is in it.").await;
+        }
+
+        let mut participants = vec![command.user.id];
+        let mentioned = string_option(args, "participants").unwrap_or_default()
+            .split_whitespace()
+            .filter_map(serenity::utils::parse_user_mention);
+        for user_id in mentioned {
+            if !participants.contains(&user_id) {
+                participants.push(user_id);
+            }
+        }
+        if participants.len() > MAX_SESSION_PARTICIPANTS {
+            let message = format!("❌ A group session can have at most {} participants.", MAX_SESSION_PARTICIPANTS);
+            return reply_ephemeral(ctx, command, &message).await;
+        }
+
+        info!("Starting group session in channel {} with {} participants", command.channel_id, participants.len());
+        let history = ConversationHistory::new(config.history_max_turns, config.history_char_budget);
+        let state = ConversationState::group(participants, history);
+        let embed = session_embed(&state, "Group Session Started");
+        conversations.insert(key, state);
+
+        let response = CreateInteractionResponse::Message(CreateInteractionResponseMessage::new().embed(embed));
+        return command.create_response(&ctx.http, response).await;
+    }
+
+    let Some(mut state) = conversations.get_mut(&key) else {
+        return reply_ephemeral(ctx, command, "❌ There is no group session in this channel. Start one with `/session start`.").await;
+    };
+    if subcommand.name != "view" && !state.is_participant(command.user.id) && !is_admin(command) {
+        return reply_ephemeral(ctx, command, "❌ Only participants can change this group session.").await;
+    }
+
+    let (embed, ephemeral) = match subcommand.name {
+        "view" => (session_embed(&state, "Group Session"), true),
+        "add" => {
+            let Some(user_id) = user_option(args, "user") else {
+                return reply_ephemeral(ctx, command, "❌ Choose someone to add.").await;
+            };
+            if state.is_participant(user_id) {
+                return reply_ephemeral(ctx, command, "❌ They are already in this session.").await;
+            }
+            if state.participants.len() >= MAX_SESSION_PARTICIPANTS {
+                let message = format!("❌ A group session can have at most {} participants.", MAX_SESSION_PARTICIPANTS);
+                return reply_ephemeral(ctx, command, &message).await;
+            }
+            state.participants.push(user_id);
+            state.update_activity();
+            (session_embed(&state, "Participant Added"), false)
+        }
+        "remove" => {
+            let Some(user_id) = user_option(args, "user") else {
+                return reply_ephemeral(ctx, command, "❌ Choose someone to remove.").await;
+            };
+            state.participants.retain(|participant| *participant != user_id);
+            if state.participants.is_empty() {
+                drop(state);
+                conversations.remove(&key);
+                return reply_ephemeral(ctx, command, "The last participant left, so the group session has ended.").await;
+            }
+            (session_embed(&state, "Participant Removed"), false)
+        }
+        "end" => {
+            drop(state);
+            conversations.remove(&key);
+            info!("Group session in channel {} ended by {}", command.channel_id, command.user.tag());
+            let response = CreateInteractionResponse::Message(
+                CreateInteractionResponseMessage::new().content("👥 Group session ended.")
+            );
+            return command.create_response(&ctx.http, response).await;
+        }
+        _ => return reply_ephemeral(ctx, command, "Unknown subcommand.").await,
+    };
+    drop(state);
+
+    let response = CreateInteractionResponse::Message(
+        CreateInteractionResponseMessage::new().embed(embed).ephemeral(ephemeral)
+    );
+    command.create_response(&ctx.http, response).await
+}
+
+pub fn register_session() -> CreateCommand {
+    CreateCommand::new("session")
+        .description("Share one conversation with Axis between several people in this channel")
+        .dm_permission(false)
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::SubCommand, "start", "Start a group session in this channel")
+                .add_sub_option(CreateCommandOption::new(CommandOptionType::String, "participants", "Mention the people joining you"))
+        )
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::SubCommand, "add", "Add someone to the group session")
+                .add_sub_option(CreateCommandOption::new(CommandOptionType::User, "user", "Who to add").required(true))
+        )
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::SubCommand, "remove", "Remove someone from the group session")
+                .add_sub_option(CreateCommandOption::new(CommandOptionType::User, "user", "Who to remove").required(true))
+        )
+        .add_option(CreateCommandOption::new(CommandOptionType::SubCommand, "view", "Show who is in the group session"))
+        .add_option(CreateCommandOption::new(CommandOptionType::SubCommand, "end", "End the group session"))
+}
+
+/// Explanation of a pasted Roblox error, pointing at the line it was raised on.
+pub fn error_embed(report: &ErrorReport, explanation: &str, from_ai: bool) -> CreateEmbed {
+    let mut fields = vec![
+        ("❗ Error", format!("```\n{}\n```", report.message.replace("```", "`\u{200b}``").chars().take(1000).collect::<String>())),
+    ];
+    if let Some(location) = &report.location {
+        fields.push(("📍 Failing Line", format!("`{}` line **{}**", location.script, location.line)));
+    }
+    if report.stack.len() > 1 {
+        let frames = report.stack.iter()
+            .map(|frame| {
+                let function = frame.function.as_deref().map(|function| format!(" in `{}`", function)).unwrap_or_default();
+                format!("• `{}` line {}{}", frame.location.script_name(), frame.location.line, function)
+            })
+            .collect();
+        fields.push(("🧵 Stack Trace", truncate_field(frames)));
+    }
+
+    let title = format!("🐞 {}", report.kind.label());
+    let source = if from_ai { "AI explanation" } else { "Built-in explanation" };
+    let footer = format!("Axis Bot • {} • Reply to ask a follow-up", source);
+    let mut parts = vec![title.as_str(), footer.as_str()];
+    parts.extend(fields.iter().flat_map(|(name, value)| [*name, value.as_str()]));
+
+    CreateEmbed::new()
+        .title(&title)
+        .color(0xED4245)
+        .description(fit_description(explanation, &parts))
+        .fields(fields.into_iter().map(|(name, value)| (name, value, false)))
+        .footer(serenity::builder::CreateEmbedFooter::new(footer))
+}
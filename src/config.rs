@@ -1,27 +1,273 @@
 use std::env;
+use std::path::PathBuf;
+use std::str::FromStr;
 use anyhow::{Result, Context};
 
+use crate::ai::intents;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProviderKind {
+    Gemini,
+    OpenAi,
+    Ollama,
+}
+
+impl ProviderKind {
+    pub fn default_model(&self) -> &'static str {
+        match self {
+            ProviderKind::Gemini => "gemini-1.5-flash-latest",
+            ProviderKind::OpenAi => "gpt-4o-mini",
+            ProviderKind::Ollama => "llama3.1",
+        }
+    }
+
+    fn default_timeout_secs(&self) -> u64 {
+        match self {
+            // Local models on development machines can be slow to answer
+            ProviderKind::Ollama => 120,
+            _ => 15,
+        }
+    }
+}
+
+impl FromStr for ProviderKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "gemini" => Ok(ProviderKind::Gemini),
+            "openai" | "openai-compatible" => Ok(ProviderKind::OpenAi),
+            "ollama" => Ok(ProviderKind::Ollama),
+            other => Err(anyhow::anyhow!("Unknown AI_PROVIDER '{}' (expected gemini, openai or ollama)", other)),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Config {
     pub discord_token: String,
-    pub gemini_api_key: String,
     pub bot_name: String,
+    pub bot_aliases: Vec<String>,
+    pub ai_provider: ProviderKind,
+    pub ai_model: String,
+    pub ai_api_key: Option<String>,
+    pub ai_base_url: Option<String>,
+    pub ai_timeout_secs: u64,
+    pub ai_max_retries: u32,
+    pub ai_breaker_threshold: u32,
+    pub ai_breaker_cooldown_secs: u64,
+    pub ai_tools_enabled: bool,
+    pub luau_max_corrections: usize,
+    pub history_max_turns: usize,
+    pub history_char_budget: usize,
+    pub reply_chain_depth: usize,
+    pub intent_min_confidence: f32,
+    pub intent_patterns_path: Option<PathBuf>,
+    pub stream_responses: bool,
+    pub stream_edit_interval_ms: u64,
+    pub max_reply_messages: usize,
+    pub code_attachment_threshold: usize,
+    pub attachment_max_bytes: u64,
+    pub data_dir: PathBuf,
+    pub user_daily_token_quota: u64,
+    pub guild_daily_token_quota: u64,
+    pub rag_index_path: PathBuf,
+    pub rag_top_k: usize,
+    pub response_cache_size: usize,
+    pub response_cache_ttl_secs: u64,
 }
 
 impl Config {
+    fn data_dir_from_env() -> PathBuf {
+        env::var("DATA_DIR")
+            .map(PathBuf::from)
+            .unwrap_or_else(|_| PathBuf::from("data"))
+    }
+
+    /// Where the documentation index is read from, and written to by `build-index`.
+    pub fn rag_index_path_from_env() -> PathBuf {
+        env::var("RAG_INDEX_PATH")
+            .map(PathBuf::from)
+            .unwrap_or_else(|_| Self::data_dir_from_env().join("rag_index.json"))
+    }
+
     pub fn from_env() -> Result<Self> {
         let discord_token = env::var("DISCORD_TOKEN")
             .context("DISCORD_TOKEN environment variable not set")?;
         
-        let gemini_api_key = env::var("GEMINI_API_KEY")
-            .context("GEMINI_API_KEY environment variable not set")?;
-        
         let bot_name = env::var("BOT_NAME").unwrap_or_else(|_| "axis".to_string());
+
+        // Other names the bot answers to, comma separated, in addition to BOT_NAME
+        let bot_aliases = env::var("BOT_ALIASES")
+            .map(|v| v.split(',').map(|alias| alias.trim().to_string()).filter(|alias| !alias.is_empty()).collect())
+            .unwrap_or_default();
+
+        let ai_provider = match env::var("AI_PROVIDER") {
+            Ok(value) => value.parse()?,
+            Err(_) => ProviderKind::Gemini,
+        };
+
+        let ai_model = env::var("AI_MODEL")
+            .unwrap_or_else(|_| ai_provider.default_model().to_string());
+
+        let vendor_key = match ai_provider {
+            ProviderKind::Gemini => env::var("GEMINI_API_KEY").ok(),
+            ProviderKind::OpenAi => env::var("OPENAI_API_KEY").ok(),
+            ProviderKind::Ollama => None,
+        };
+        let ai_api_key = env::var("AI_API_KEY").ok().or(vendor_key);
+
+        if ai_provider == ProviderKind::Gemini && ai_api_key.is_none() {
+            return Err(anyhow::anyhow!("GEMINI_API_KEY environment variable not set"));
+        }
+
+        let ai_base_url = env::var("AI_BASE_URL").ok();
+
+        let ai_timeout_secs = env::var("AI_TIMEOUT_SECS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or_else(|| ai_provider.default_timeout_secs());
+
+        let ai_max_retries = env::var("AI_MAX_RETRIES")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(3);
+
+        let ai_breaker_threshold = env::var("AI_BREAKER_THRESHOLD")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(5);
+
+        let ai_breaker_cooldown_secs = env::var("AI_BREAKER_COOLDOWN_SECS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(60);
+
+        // Some local models reject requests that declare functions
+        let ai_tools_enabled = env::var("AI_TOOLS_ENABLED")
+            .map(|v| !matches!(v.trim().to_lowercase().as_str(), "0" | "false" | "no"))
+            .unwrap_or(true);
+
+        // Zero still checks and tags Luau code, without asking for fixes
+        let luau_max_corrections = env::var("LUAU_MAX_CORRECTIONS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(2);
+
+        let history_max_turns = env::var("HISTORY_MAX_TURNS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(20);
+
+        let history_char_budget = env::var("HISTORY_CHAR_BUDGET")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(8000);
+
+        // Zero leaves replied-to messages out of the prompt
+        let reply_chain_depth = env::var("REPLY_CHAIN_DEPTH")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(5);
+
+        // Above 1 no request is answered locally, so every message goes to the model
+        let intent_min_confidence = env::var("INTENT_MIN_CONFIDENCE")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(intents::DEFAULT_MIN_CONFIDENCE);
+
+        // Replaces the built-in patterns in src/ai/intents.json
+        let intent_patterns_path = env::var("INTENT_PATTERNS_PATH").ok().map(PathBuf::from);
+
+        let stream_responses = env::var("STREAM_RESPONSES")
+            .map(|v| matches!(v.trim().to_lowercase().as_str(), "1" | "true" | "yes"))
+            .unwrap_or(false);
+
+        // Discord allows roughly five edits per five seconds on a channel
+        let stream_edit_interval_ms = env::var("STREAM_EDIT_INTERVAL_MS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(1200);
+
+        let max_reply_messages = env::var("MAX_REPLY_MESSAGES")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(3);
+
+        // Zero keeps every code block inline
+        let code_attachment_threshold = env::var("CODE_ATTACHMENT_THRESHOLD")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(1200);
+
+        let attachment_max_bytes = env::var("ATTACHMENT_MAX_BYTES")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(4 * 1024 * 1024);
+
+        let data_dir = Self::data_dir_from_env();
+
+        // Zero disables the quota
+        let user_daily_token_quota = env::var("USER_DAILY_TOKEN_QUOTA")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(0);
+
+        let guild_daily_token_quota = env::var("GUILD_DAILY_TOKEN_QUOTA")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(0);
+
+        let rag_index_path = Self::rag_index_path_from_env();
+
+        // Zero disables documentation lookups
+        let rag_top_k = env::var("RAG_TOP_K")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(4);
+
+        // Zero disables the response cache
+        let response_cache_size = env::var("RESPONSE_CACHE_SIZE")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(256);
+
+        let response_cache_ttl_secs = env::var("RESPONSE_CACHE_TTL_SECS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(3600);
         
         Ok(Config {
             discord_token,
-            gemini_api_key,
             bot_name,
+            bot_aliases,
+            ai_provider,
+            ai_model,
+            ai_api_key,
+            ai_base_url,
+            ai_timeout_secs,
+            ai_max_retries,
+            ai_breaker_threshold,
+            ai_breaker_cooldown_secs,
+            ai_tools_enabled,
+            luau_max_corrections,
+            history_max_turns,
+            history_char_budget,
+            reply_chain_depth,
+            intent_min_confidence,
+            intent_patterns_path,
+            stream_responses,
+            stream_edit_interval_ms,
+            max_reply_messages,
+            code_attachment_threshold,
+            attachment_max_bytes,
+            data_dir,
+            user_daily_token_quota,
+            guild_daily_token_quota,
+            rag_index_path,
+            rag_top_k,
+            response_cache_size,
+            response_cache_ttl_secs,
         })
     }
 }
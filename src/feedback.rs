@@ -0,0 +1,62 @@
+use anyhow::Result;
+use serde::Serialize;
+use serenity::model::id::{GuildId, UserId};
+use serenity::model::Timestamp;
+use std::path::PathBuf;
+use tokio::sync::Mutex;
+use tracing::debug;
+
+use crate::storage;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Rating {
+    Up,
+    Down,
+}
+
+/// One 👍 or 👎 on an AI answer, as written to the feedback log.
+#[derive(Debug, Serialize)]
+pub struct FeedbackEntry<'a> {
+    pub timestamp: String,
+    pub user_id: UserId,
+    pub guild_id: Option<GuildId>,
+    pub rating: Rating,
+    pub model: &'a str,
+    pub prompt: &'a str,
+    pub response: &'a str,
+}
+
+impl<'a> FeedbackEntry<'a> {
+    pub fn new(user_id: UserId, guild_id: Option<GuildId>, rating: Rating, model: &'a str, prompt: &'a str, response: &'a str) -> Self {
+        Self {
+            timestamp: Timestamp::now().to_string(),
+            user_id,
+            guild_id,
+            rating,
+            model,
+            prompt,
+            response,
+        }
+    }
+}
+
+/// Append-only JSON Lines log of answer ratings, kept for reviewing answer quality.
+pub struct FeedbackStore {
+    path: PathBuf,
+    /// Keeps concurrent appends from interleaving.
+    lock: Mutex<()>,
+}
+
+impl FeedbackStore {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path, lock: Mutex::new(()) }
+    }
+
+    pub async fn record(&self, entry: &FeedbackEntry<'_>) -> Result<()> {
+        let _guard = self.lock.lock().await;
+        storage::append_jsonl(&self.path, entry).await?;
+        debug!("Recorded {:?} feedback from user {}", entry.rating, entry.user_id);
+        Ok(())
+    }
+}
@@ -0,0 +1,135 @@
+use serde::{Deserialize, Serialize};
+use serenity::model::id::GuildId;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use tokio::sync::Mutex;
+use tracing::{debug, error};
+
+use crate::ai::safety::SafetySettings;
+use crate::storage;
+
+/// Where the bot holds conversations started in a server's channels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConversationMode {
+    /// Reply directly in the channel.
+    #[default]
+    Inline,
+    /// Open a public thread off the triggering message.
+    PublicThread,
+    /// Open a private thread with only the user and the bot.
+    PrivateThread,
+}
+
+impl ConversationMode {
+    pub const ALL: [ConversationMode; 3] = [
+        ConversationMode::Inline,
+        ConversationMode::PublicThread,
+        ConversationMode::PrivateThread,
+    ];
+
+    pub fn key(&self) -> &'static str {
+        match self {
+            ConversationMode::Inline => "inline",
+            ConversationMode::PublicThread => "public_thread",
+            ConversationMode::PrivateThread => "private_thread",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ConversationMode::Inline => "Inline",
+            ConversationMode::PublicThread => "Public thread",
+            ConversationMode::PrivateThread => "Private thread",
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|mode| mode.key() == key)
+    }
+}
+
+/// Which messages in a server's channels the bot answers outside a conversation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TriggerMode {
+    /// Only @mentions of the bot or its role, and replies to it.
+    MentionOnly,
+    /// Also its name and aliases, pasted errors and Roblox questions.
+    #[default]
+    KeywordPassive,
+}
+
+impl TriggerMode {
+    pub const ALL: [TriggerMode; 2] = [TriggerMode::MentionOnly, TriggerMode::KeywordPassive];
+
+    pub fn key(&self) -> &'static str {
+        match self {
+            TriggerMode::MentionOnly => "mention_only",
+            TriggerMode::KeywordPassive => "keyword_passive",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            TriggerMode::MentionOnly => "Mention only",
+            TriggerMode::KeywordPassive => "Keyword passive",
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|mode| mode.key() == key)
+    }
+}
+
+/// Per-server configuration changed through slash commands.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GuildSettings {
+    #[serde(default)]
+    pub safety: SafetySettings,
+    /// Custom system prompt template; `None` uses the built-in persona.
+    #[serde(default)]
+    pub persona: Option<String>,
+    #[serde(default)]
+    pub conversation_mode: ConversationMode,
+    #[serde(default)]
+    pub trigger_mode: TriggerMode,
+}
+
+/// Guild settings persisted as JSON. Servers that never changed anything
+/// get the defaults, as do direct messages.
+pub struct GuildSettingsStore {
+    guilds: Mutex<HashMap<GuildId, GuildSettings>>,
+    path: PathBuf,
+}
+
+impl GuildSettingsStore {
+    pub fn load(path: PathBuf) -> Self {
+        Self {
+            guilds: Mutex::new(storage::load_json(&path)),
+            path,
+        }
+    }
+
+    pub async fn get(&self, guild_id: Option<GuildId>) -> GuildSettings {
+        let Some(guild_id) = guild_id else {
+            return GuildSettings::default();
+        };
+        self.guilds.lock().await.get(&guild_id).cloned().unwrap_or_default()
+    }
+
+    /// Applies `change` to the guild's settings and saves them, returning the result.
+    pub async fn update(&self, guild_id: GuildId, change: impl FnOnce(&mut GuildSettings)) -> GuildSettings {
+        let mut guilds = self.guilds.lock().await;
+        let settings = guilds.entry(guild_id).or_default();
+        change(settings);
+        let updated = settings.clone();
+
+        debug!("Updated settings for guild {}", guild_id);
+
+        if let Err(e) = storage::save_json(&self.path, &*guilds).await {
+            error!("Failed to persist guild settings: {}", e);
+        }
+        updated
+    }
+}
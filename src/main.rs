@@ -2,13 +2,17 @@ mod ai;
 mod bot;
 mod commands;
 mod config;
+mod feedback;
+mod guild_settings;
+mod rag;
+mod storage;
+mod usage;
 
 use anyhow::Result;
 use bot::{Handler, ShardManagerContainer};
 use config::Config;
 use serenity::prelude::*;
 use tracing::{error, info};
-use tracing_subscriber;
 
 #[tokio::main]
 async fn main() -> Result<()> {
@@ -18,12 +22,18 @@ async fn main() -> Result<()> {
         .with_thread_ids(true)
         .init();
     
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("build-index") {
+        return rag::build_index_command(&args[1..], Config::rag_index_path_from_env()).await;
+    }
+
     info!("Starting Axis bot...");
 
     let config = match Config::from_env() {
         Ok(config) => {
             info!("Configuration loaded successfully");
             info!("Bot name: {}", config.bot_name);
+            info!("AI provider: {:?} ({})", config.ai_provider, config.ai_model);
             config
         }
         Err(e) => {
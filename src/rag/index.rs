@@ -0,0 +1,137 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// BM25 term frequency saturation.
+const K1: f32 = 1.2;
+/// BM25 document length normalization.
+const B: f32 = 0.75;
+
+const STOP_WORDS: &[&str] = &[
+    "a", "an", "and", "are", "as", "at", "be", "by", "can", "do", "does", "for", "from", "how",
+    "i", "if", "in", "is", "it", "me", "my", "of", "on", "or", "so", "that", "the", "this", "to",
+    "use", "what", "when", "which", "why", "with", "you", "your",
+];
+
+/// A chunk of documentation that can be quoted to the model.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Passage {
+    pub title: String,
+    /// Public page the passage comes from, used for citations.
+    pub url: Option<String>,
+    pub text: String,
+}
+
+/// Splits text into lowercase search terms. Identifiers are kept whole and also
+/// split at case changes, so `GetService` matches both `getservice` and `service`.
+pub fn tokenize(text: &str) -> Vec<String> {
+    let mut terms = Vec::new();
+    for word in text.split(|c: char| !c.is_alphanumeric()).filter(|word| !word.is_empty()) {
+        let lower = word.to_lowercase();
+        if !STOP_WORDS.contains(&lower.as_str()) {
+            terms.push(lower.clone());
+        }
+
+        let parts = split_camel_case(word);
+        if parts.len() > 1 {
+            terms.extend(parts.into_iter()
+                .map(|part| part.to_lowercase())
+                .filter(|part| part.len() > 1 && !STOP_WORDS.contains(&part.as_str())));
+        }
+    }
+    terms
+}
+
+fn split_camel_case(word: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut start = 0;
+    let chars: Vec<(usize, char)> = word.char_indices().collect();
+    for window in chars.windows(2) {
+        let ((_, prev), (index, next)) = (window[0], window[1]);
+        if prev.is_lowercase() && next.is_uppercase() {
+            parts.push(&word[start..index]);
+            start = index;
+        }
+    }
+    parts.push(&word[start..]);
+    parts
+}
+
+/// Inverted index over documentation passages, ranked with BM25.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct DocIndex {
+    passages: Vec<Passage>,
+    /// Number of terms in each passage.
+    lengths: Vec<u32>,
+    /// Term -> (passage index, term frequency).
+    postings: HashMap<String, Vec<(u32, u32)>>,
+}
+
+impl DocIndex {
+    pub fn build(passages: Vec<Passage>) -> Self {
+        let mut lengths = Vec::with_capacity(passages.len());
+        let mut postings: HashMap<String, Vec<(u32, u32)>> = HashMap::new();
+
+        for (index, passage) in passages.iter().enumerate() {
+            // Titles name the API or topic, so they count twice
+            let mut terms = tokenize(&passage.title);
+            terms.extend(tokenize(&passage.title));
+            terms.extend(tokenize(&passage.text));
+            lengths.push(terms.len() as u32);
+
+            let mut frequencies: HashMap<String, u32> = HashMap::new();
+            for term in terms {
+                *frequencies.entry(term).or_default() += 1;
+            }
+            for (term, frequency) in frequencies {
+                postings.entry(term).or_default().push((index as u32, frequency));
+            }
+        }
+
+        Self { passages, lengths, postings }
+    }
+
+    pub fn len(&self) -> usize {
+        self.passages.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.passages.is_empty()
+    }
+
+    /// The `limit` passages that best match `query`, best first, with their scores.
+    pub fn search(&self, query: &str, limit: usize) -> Vec<(&Passage, f32)> {
+        if self.passages.is_empty() || limit == 0 {
+            return Vec::new();
+        }
+
+        let count = self.passages.len() as f32;
+        let average_length = self.lengths.iter().map(|&len| len as f32).sum::<f32>() / count;
+        let mut scores: HashMap<u32, f32> = HashMap::new();
+
+        let mut terms = tokenize(query);
+        terms.sort();
+        terms.dedup();
+
+        for term in &terms {
+            let Some(postings) = self.postings.get(term) else {
+                continue;
+            };
+            let document_frequency = postings.len() as f32;
+            let idf = ((count - document_frequency + 0.5) / (document_frequency + 0.5) + 1.0).ln();
+
+            for &(index, frequency) in postings {
+                let frequency = frequency as f32;
+                let length = self.lengths[index as usize] as f32;
+                let normalized = frequency * (K1 + 1.0) / (frequency + K1 * (1.0 - B + B * length / average_length));
+                *scores.entry(index).or_default() += idf * normalized;
+            }
+        }
+
+        let mut ranked: Vec<(u32, f32)> = scores.into_iter().collect();
+        ranked.sort_by(|a, b| b.1.total_cmp(&a.1));
+        ranked.into_iter()
+            .take(limit)
+            .map(|(index, score)| (&self.passages[index as usize], score))
+            .collect()
+    }
+}
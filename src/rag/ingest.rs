@@ -0,0 +1,254 @@
+use anyhow::{Context, Result};
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use tracing::{debug, info, warn};
+
+use super::index::Passage;
+
+const DOCS_BASE_URL: &str = "https://create.roblox.com/docs";
+
+/// Longest passage stored in the index, in characters.
+const MAX_PASSAGE_CHARS: usize = 1500;
+
+/// Sections shorter than this carry too little to be worth retrieving.
+const MIN_SECTION_CHARS: usize = 40;
+
+fn markdown_files(dir: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    let entries = std::fs::read_dir(dir).with_context(|| format!("Failed to read {}", dir.display()))?;
+    for entry in entries {
+        let path = entry?.path();
+        if path.is_dir() {
+            markdown_files(&path, files)?;
+        } else if path.extension().is_some_and(|ext| ext == "md") {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Creator docs URL for a markdown file, e.g. `en-us/scripting/services.md`
+/// becomes `https://create.roblox.com/docs/scripting/services`.
+fn docs_url(root: &Path, path: &Path) -> Option<String> {
+    let relative = path.strip_prefix(root).ok()?.with_extension("");
+    let relative = relative.to_string_lossy().replace('\\', "/");
+    let relative = relative.split_once("en-us/").map_or(relative.as_str(), |(_, rest)| rest);
+    let relative = if relative == "index" { "" } else { relative.trim_end_matches("/index") };
+    Some(format!("{}/{}", DOCS_BASE_URL, relative.trim_matches('/')))
+}
+
+/// Splits `text` at paragraph boundaries into pieces of at most `MAX_PASSAGE_CHARS`.
+/// Paragraphs that are too long on their own (such as member lists) are split by line.
+fn chunk(text: &str) -> Vec<String> {
+    let mut units: Vec<(String, &str)> = Vec::new();
+    for paragraph in text.split("\n\n").map(str::trim).filter(|p| !p.is_empty()) {
+        if paragraph.chars().count() <= MAX_PASSAGE_CHARS {
+            units.push((paragraph.to_string(), "\n\n"));
+        } else {
+            units.extend(paragraph.lines().map(|line| (line.chars().take(MAX_PASSAGE_CHARS).collect(), "\n")));
+        }
+    }
+
+    let mut chunks = Vec::new();
+    let mut current = String::new();
+    for (unit, separator) in units {
+        if !current.is_empty() && current.chars().count() + unit.chars().count() + separator.len() > MAX_PASSAGE_CHARS {
+            chunks.push(std::mem::take(&mut current));
+        }
+        if !current.is_empty() {
+            current.push_str(separator);
+        }
+        current.push_str(&unit);
+    }
+    if !current.is_empty() {
+        chunks.push(current);
+    }
+    chunks
+}
+
+/// Splits a markdown page into passages, one or more per heading section.
+fn markdown_passages(contents: &str, url: Option<String>, fallback_title: &str) -> Vec<Passage> {
+    let mut body = contents;
+    let mut page_title = None;
+
+    // YAML front matter holds the page title
+    if let Some(rest) = contents.strip_prefix("---") {
+        if let Some((front_matter, rest)) = rest.split_once("\n---") {
+            page_title = front_matter.lines()
+                .find_map(|line| line.strip_prefix("title:"))
+                .map(|title| title.trim().trim_matches('"').to_string());
+            body = rest;
+        }
+    }
+
+    let mut sections: Vec<(Option<String>, String)> = vec![(None, String::new())];
+    let mut in_code = false;
+    for line in body.lines() {
+        if line.trim_start().starts_with("```") {
+            in_code = !in_code;
+        }
+        let heading = (!in_code)
+            .then(|| line.trim_start_matches('#'))
+            .filter(|rest| line.starts_with('#') && rest.starts_with(' '));
+        match heading {
+            Some(heading) if page_title.is_none() && line.starts_with("# ") => {
+                page_title = Some(heading.trim().to_string());
+            }
+            Some(heading) => sections.push((Some(heading.trim().to_string()), String::new())),
+            None => {
+                if let Some((_, text)) = sections.last_mut() {
+                    text.push_str(line);
+                    text.push('\n');
+                }
+            }
+        }
+    }
+
+    let page_title = page_title.unwrap_or_else(|| fallback_title.to_string());
+    sections.into_iter()
+        .filter(|(_, text)| text.trim().chars().count() >= MIN_SECTION_CHARS)
+        .flat_map(|(heading, text)| {
+            let title = match heading {
+                Some(heading) => format!("{} › {}", page_title, heading),
+                None => page_title.clone(),
+            };
+            let url = url.clone();
+            chunk(&text).into_iter().map(move |text| Passage { title: title.clone(), url: url.clone(), text })
+        })
+        .collect()
+}
+
+/// Reads every `.md` file under `root` (for example a checkout of the Roblox
+/// creator-docs `content/en-us` directory).
+pub fn docs_passages(root: &Path) -> Result<Vec<Passage>> {
+    let mut files = Vec::new();
+    markdown_files(root, &mut files)?;
+    files.sort();
+
+    let mut passages = Vec::new();
+    for path in &files {
+        let contents = match std::fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) => {
+                warn!("Skipping {}: {}", path.display(), e);
+                continue;
+            }
+        };
+        let fallback_title = path.file_stem().map(|stem| stem.to_string_lossy().to_string()).unwrap_or_default();
+        let page = markdown_passages(&contents, docs_url(root, path), &fallback_title);
+        debug!("{}: {} passages", path.display(), page.len());
+        passages.extend(page);
+    }
+
+    info!("Read {} passages from {} markdown files", passages.len(), files.len());
+    Ok(passages)
+}
+
+fn type_name(value: &Value) -> &str {
+    value["Name"].as_str().unwrap_or("any")
+}
+
+fn tags(value: &Value) -> Vec<&str> {
+    value["Tags"].as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_str)
+        .collect()
+}
+
+fn describe_member(member: &Value) -> Option<(&str, String)> {
+    let kind = member["MemberType"].as_str()?;
+    let name = member["Name"].as_str()?;
+
+    let parameters = || member["Parameters"].as_array()
+        .into_iter()
+        .flatten()
+        .map(|parameter| format!("{}: {}", parameter["Name"].as_str().unwrap_or("_"), type_name(&parameter["Type"])))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    let mut line = match kind {
+        "Property" => format!("{}: {}", name, type_name(&member["ValueType"])),
+        "Function" | "Callback" => format!("{}({}): {}", name, parameters(), type_name(&member["ReturnType"])),
+        _ => format!("{}({})", name, parameters()),
+    };
+    let member_tags = tags(member);
+    if member_tags.contains(&"Deprecated") {
+        line.push_str(" (deprecated)");
+    } else if member_tags.contains(&"ReadOnly") {
+        line.push_str(" (read-only)");
+    }
+    Some((kind, line))
+}
+
+fn class_passages(class: &Value) -> Vec<Passage> {
+    let Some(name) = class["Name"].as_str() else {
+        return Vec::new();
+    };
+
+    let mut header = format!("Class {}", name);
+    if let Some(superclass) = class["Superclass"].as_str().filter(|s| *s != "<<<ROOT>>>") {
+        header.push_str(&format!(" (inherits {})", superclass));
+    }
+    let class_tags = tags(class);
+    if !class_tags.is_empty() {
+        header.push_str(&format!(". Tags: {}", class_tags.join(", ")));
+    }
+
+    let members: Vec<(&str, String)> = class["Members"].as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(describe_member)
+        .collect();
+
+    let mut text = header.clone();
+    for (kind, heading) in [("Property", "Properties"), ("Function", "Methods"), ("Event", "Events"), ("Callback", "Callbacks")] {
+        let lines: Vec<&String> = members.iter().filter(|(k, _)| *k == kind).map(|(_, line)| line).collect();
+        if !lines.is_empty() {
+            text.push_str(&format!("\n\n{}:\n{}", heading, lines.iter().map(|line| format!("- {}", line)).collect::<Vec<_>>().join("\n")));
+        }
+    }
+
+    let url = Some(format!("{}/reference/engine/classes/{}", DOCS_BASE_URL, name));
+    let chunks = chunk(&text);
+    let total = chunks.len();
+    chunks.into_iter()
+        .enumerate()
+        .map(|(index, text)| Passage {
+            title: if index == 0 { format!("{} API", name) } else { format!("{} API (part {}/{})", name, index + 1, total) },
+            url: url.clone(),
+            text: if index == 0 { text } else { format!("{}\n\n{}", header, text) },
+        })
+        .collect()
+}
+
+fn enum_passage(enumeration: &Value) -> Option<Passage> {
+    let name = enumeration["Name"].as_str()?;
+    let items: Vec<String> = enumeration["Items"].as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|item| Some(format!("{} = {}", item["Name"].as_str()?, item["Value"].as_i64().unwrap_or_default())))
+        .collect();
+
+    let text: String = format!("Enum.{} items: {}", name, items.join(", ")).chars().take(MAX_PASSAGE_CHARS).collect();
+    Some(Passage {
+        title: format!("Enum.{}", name),
+        url: Some(format!("{}/reference/engine/enums/{}", DOCS_BASE_URL, name)),
+        text,
+    })
+}
+
+/// Reads the classes and enums of a Roblox `API-Dump.json`.
+pub fn api_dump_passages(path: &Path) -> Result<Vec<Passage>> {
+    let contents = std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let dump: Value = serde_json::from_str(&contents).with_context(|| format!("Failed to parse {}", path.display()))?;
+
+    let mut passages: Vec<Passage> = dump["Classes"].as_array()
+        .into_iter()
+        .flatten()
+        .flat_map(class_passages)
+        .collect();
+    passages.extend(dump["Enums"].as_array().into_iter().flatten().filter_map(enum_passage));
+
+    info!("Read {} passages from {}", passages.len(), path.display());
+    Ok(passages)
+}
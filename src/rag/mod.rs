@@ -0,0 +1,97 @@
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+use tracing::{debug, info};
+
+mod index;
+mod ingest;
+
+pub use index::DocIndex;
+
+use crate::storage;
+
+/// Passages scoring below this are too loosely related to be worth quoting.
+const MIN_SCORE: f32 = 3.0;
+
+/// Loads the index written by `build-index`, or an empty one if it has not been built.
+pub fn load(path: &Path) -> DocIndex {
+    let index: DocIndex = storage::load_json(path);
+    if index.is_empty() {
+        info!("No documentation index at {}, answers will not cite docs", path.display());
+    } else {
+        info!("Documentation index has {} passages", index.len());
+    }
+    index
+}
+
+/// System prompt section quoting the passages that best match `question`, or
+/// `None` when nothing relevant was found.
+pub fn context_for(index: &DocIndex, question: &str, limit: usize) -> Option<String> {
+    let passages: Vec<_> = index.search(question, limit)
+        .into_iter()
+        .filter(|(_, score)| *score >= MIN_SCORE)
+        .collect();
+    if passages.is_empty() {
+        return None;
+    }
+
+    let mut context = String::from(
+        "REFERENCE DOCUMENTATION:\n\
+        The passages below were retrieved from the official Roblox documentation for this question. \
+        Prefer them over memory, never invent API members that they contradict, and cite the passages \
+        you rely on as [1], [2], ... in your answer. End with a \"Sources:\" line listing the cited links \
+        wrapped in <> so Discord does not embed them.\n",
+    );
+    for (number, (passage, score)) in passages.iter().enumerate() {
+        debug!("Retrieved '{}' (score {:.2})", passage.title, score);
+        context.push_str(&format!("\n[{}] {}", number + 1, passage.title));
+        if let Some(url) = &passage.url {
+            context.push_str(&format!(" ({})", url));
+        }
+        context.push_str(&format!("\n{}\n", passage.text));
+    }
+    Some(context)
+}
+
+fn usage_error() -> anyhow::Error {
+    anyhow::anyhow!("Usage: axis-bot build-index [--docs <markdown dir>] [--api-dump <API-Dump.json>] [--out <index file>]")
+}
+
+/// `axis-bot build-index`: reads the docs and API dump and writes the index to disk.
+///
+/// Sources default to `RAG_DOCS_DIR` and `RAG_API_DUMP`, the output to the
+/// index path the bot loads from.
+pub async fn build_index_command(args: &[String], default_out: PathBuf) -> Result<()> {
+    let mut docs = std::env::var("RAG_DOCS_DIR").ok().map(PathBuf::from);
+    let mut api_dump = std::env::var("RAG_API_DUMP").ok().map(PathBuf::from);
+    let mut out = default_out;
+
+    let mut args = args.iter();
+    while let Some(flag) = args.next() {
+        let value = args.next().map(PathBuf::from).ok_or_else(usage_error)?;
+        match flag.as_str() {
+            "--docs" => docs = Some(value),
+            "--api-dump" => api_dump = Some(value),
+            "--out" => out = value,
+            _ => return Err(usage_error()),
+        }
+    }
+
+    if docs.is_none() && api_dump.is_none() {
+        return Err(usage_error());
+    }
+
+    let mut passages = Vec::new();
+    if let Some(docs) = &docs {
+        passages.extend(ingest::docs_passages(docs)?);
+    }
+    if let Some(api_dump) = &api_dump {
+        passages.extend(ingest::api_dump_passages(api_dump)?);
+    }
+
+    let index = DocIndex::build(passages);
+    storage::save_json(&out, &index).await
+        .with_context(|| format!("Failed to write index to {}", out.display()))?;
+
+    info!("Wrote {} passages to {}", index.len(), out.display());
+    Ok(())
+}
@@ -0,0 +1,59 @@
+use anyhow::{Context, Result};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::path::Path;
+use tracing::{info, warn};
+
+/// Loads a JSON document from `path`, falling back to the default value when
+/// the file does not exist yet or cannot be parsed.
+pub fn load_json<T: DeserializeOwned + Default>(path: &Path) -> T {
+    match std::fs::read_to_string(path) {
+        Ok(contents) => match serde_json::from_str(&contents) {
+            Ok(value) => {
+                info!("Loaded {}", path.display());
+                value
+            }
+            Err(e) => {
+                warn!("Ignoring unreadable {}: {}", path.display(), e);
+                T::default()
+            }
+        },
+        Err(_) => T::default(),
+    }
+}
+
+/// Writes `value` as JSON to `path`, replacing the file atomically.
+pub async fn save_json<T: Serialize>(path: &Path, value: &T) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        tokio::fs::create_dir_all(dir).await
+            .with_context(|| format!("Failed to create {}", dir.display()))?;
+    }
+
+    let contents = serde_json::to_string_pretty(value)?;
+    let tmp = path.with_extension("json.tmp");
+    tokio::fs::write(&tmp, contents).await
+        .with_context(|| format!("Failed to write {}", tmp.display()))?;
+    tokio::fs::rename(&tmp, path).await
+        .with_context(|| format!("Failed to replace {}", path.display()))?;
+
+    Ok(())
+}
+
+/// Appends `value` to `path` as one line of JSON, creating the file if needed.
+pub async fn append_jsonl<T: Serialize>(path: &Path, value: &T) -> Result<()> {
+    use tokio::io::AsyncWriteExt;
+
+    if let Some(dir) = path.parent() {
+        tokio::fs::create_dir_all(dir).await
+            .with_context(|| format!("Failed to create {}", dir.display()))?;
+    }
+
+    let mut line = serde_json::to_string(value)?;
+    line.push('\n');
+    let mut file = tokio::fs::OpenOptions::new().create(true).append(true).open(path).await
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+    file.write_all(line.as_bytes()).await
+        .with_context(|| format!("Failed to append to {}", path.display()))?;
+
+    Ok(())
+}
@@ -0,0 +1,174 @@
+use serde::{Deserialize, Serialize};
+use serenity::model::id::{GuildId, UserId};
+use serenity::model::Timestamp;
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use tokio::sync::Mutex;
+use tracing::{debug, error};
+
+use crate::storage;
+
+/// Number of days of usage kept on disk.
+const RETENTION_DAYS: usize = 31;
+
+/// Token counts reported by the model provider.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Usage {
+    pub prompt_tokens: u64,
+    pub output_tokens: u64,
+}
+
+impl std::ops::AddAssign for Usage {
+    fn add_assign(&mut self, other: Usage) {
+        self.prompt_tokens += other.prompt_tokens;
+        self.output_tokens += other.output_tokens;
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct TokenCounts {
+    pub requests: u64,
+    pub prompt_tokens: u64,
+    pub output_tokens: u64,
+}
+
+impl TokenCounts {
+    pub fn total(&self) -> u64 {
+        self.prompt_tokens + self.output_tokens
+    }
+
+    fn add(&mut self, usage: Usage) {
+        self.requests += 1;
+        self.prompt_tokens += usage.prompt_tokens;
+        self.output_tokens += usage.output_tokens;
+    }
+
+    fn merge(&mut self, other: &TokenCounts) {
+        self.requests += other.requests;
+        self.prompt_tokens += other.prompt_tokens;
+        self.output_tokens += other.output_tokens;
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct DayUsage {
+    users: HashMap<UserId, TokenCounts>,
+    guilds: HashMap<GuildId, TokenCounts>,
+}
+
+/// Which daily quota a request ran into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuotaExceeded {
+    User,
+    Guild,
+}
+
+impl QuotaExceeded {
+    pub fn user_message(&self) -> &'static str {
+        match self {
+            QuotaExceeded::User => "You have reached your daily AI usage limit. It resets at 00:00 UTC.",
+            QuotaExceeded::Guild => "This server has reached its daily AI usage limit. It resets at 00:00 UTC.",
+        }
+    }
+}
+
+/// Tracks prompt and output tokens per user, per guild and per UTC day, and
+/// enforces the configured daily quotas. Quotas of zero mean unlimited.
+pub struct UsageTracker {
+    days: Mutex<BTreeMap<String, DayUsage>>,
+    path: PathBuf,
+    user_daily_quota: u64,
+    guild_daily_quota: u64,
+}
+
+impl UsageTracker {
+    pub fn load(path: PathBuf, user_daily_quota: u64, guild_daily_quota: u64) -> Self {
+        Self {
+            days: Mutex::new(storage::load_json(&path)),
+            path,
+            user_daily_quota,
+            guild_daily_quota,
+        }
+    }
+
+    fn day_key(days_ago: i64) -> String {
+        let secs = Timestamp::now().unix_timestamp() - days_ago * 86_400;
+        Timestamp::from_unix_timestamp(secs)
+            .map(|ts| ts.date().to_string())
+            .unwrap_or_default()
+    }
+
+    pub async fn check_quota(&self, user_id: UserId, guild_id: Option<GuildId>) -> Result<(), QuotaExceeded> {
+        let days = self.days.lock().await;
+        let Some(today) = days.get(&Self::day_key(0)) else {
+            return Ok(());
+        };
+
+        if self.user_daily_quota > 0
+            && today.users.get(&user_id).is_some_and(|c| c.total() >= self.user_daily_quota)
+        {
+            return Err(QuotaExceeded::User);
+        }
+
+        if let Some(guild_id) = guild_id {
+            if self.guild_daily_quota > 0
+                && today.guilds.get(&guild_id).is_some_and(|c| c.total() >= self.guild_daily_quota)
+            {
+                return Err(QuotaExceeded::Guild);
+            }
+        }
+
+        Ok(())
+    }
+
+    pub async fn record(&self, user_id: UserId, guild_id: Option<GuildId>, usage: Usage) {
+        let mut days = self.days.lock().await;
+        let today = days.entry(Self::day_key(0)).or_default();
+        today.users.entry(user_id).or_default().add(usage);
+        if let Some(guild_id) = guild_id {
+            today.guilds.entry(guild_id).or_default().add(usage);
+        }
+
+        while days.len() > RETENTION_DAYS {
+            days.pop_first();
+        }
+
+        debug!("Recorded {} prompt + {} output tokens for user {}", usage.prompt_tokens, usage.output_tokens, user_id);
+
+        if let Err(e) = storage::save_json(&self.path, &*days).await {
+            error!("Failed to persist usage: {}", e);
+        }
+    }
+
+    async fn sum_days<F>(&self, days_back: i64, select: F) -> TokenCounts
+    where
+        F: Fn(&DayUsage) -> Option<TokenCounts>,
+    {
+        let days = self.days.lock().await;
+        let mut total = TokenCounts::default();
+        for days_ago in 0..days_back {
+            if let Some(counts) = days.get(&Self::day_key(days_ago)).and_then(&select) {
+                total.merge(&counts);
+            }
+        }
+        total
+    }
+
+    /// Usage of a user over the last `days` UTC days, including today.
+    pub async fn user_usage(&self, user_id: UserId, days: i64) -> TokenCounts {
+        self.sum_days(days, |day| day.users.get(&user_id).copied()).await
+    }
+
+    /// Usage of a guild over the last `days` UTC days, including today.
+    pub async fn guild_usage(&self, guild_id: GuildId, days: i64) -> TokenCounts {
+        self.sum_days(days, |day| day.guilds.get(&guild_id).copied()).await
+    }
+
+    pub fn user_daily_quota(&self) -> u64 {
+        self.user_daily_quota
+    }
+
+    pub fn guild_daily_quota(&self) -> u64 {
+        self.guild_daily_quota
+    }
+}